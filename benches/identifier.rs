@@ -26,7 +26,7 @@ fn bench_nested_id_translator(c: &mut Criterion) {
     let mut group = c.benchmark_group("Nested identifier/translator");
 
     for i in 0usize..=100usize {
-        let (mut vpa, test_string) = nested_id(i * 100);
+        let (vpa, test_string) = nested_id(i * 100);
         group.bench_function(BenchmarkId::from_parameter(i * 100), |b| {
             b.iter(|| vpa.translate(black_box(&test_string)))
         });
@@ -38,7 +38,7 @@ fn bench_nested_id_recognizer(c: &mut Criterion) {
     let mut group = c.benchmark_group("Nested identifier/recognizer");
 
     for i in 0usize..=100usize {
-        let (mut vpa, test_string) = nested_id_recognizer(i * 100);
+        let (vpa, test_string) = nested_id_recognizer(i * 100);
         group.bench_function(BenchmarkId::from_parameter(i * 100), |b| {
             b.iter(|| vpa.recognize(black_box(&test_string)))
         });
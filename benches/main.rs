@@ -1,3 +1,6 @@
+// Only the groups passed to `criterion_main!` are run, the other benchmarks are kept to switch between.
+#![allow(dead_code)]
+
 use criterion::criterion_main;
 
 mod generation;
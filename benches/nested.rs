@@ -33,7 +33,7 @@ fn bench_nested_translator(c: &mut Criterion) {
     let mut group = c.benchmark_group("Nested words/translator");
 
     for i in 1usize..=100usize {
-        let (mut vpa, test_string) = nested_translator(i * 100);
+        let (vpa, test_string) = nested_translator(i * 100);
         group.bench_function(BenchmarkId::from_parameter(i * 100), |b| {
             b.iter(|| vpa.translate(black_box(&test_string)))
         });
@@ -45,7 +45,7 @@ fn bench_nested_recognizer(c: &mut Criterion) {
     let mut group = c.benchmark_group("Nested words/recognizer");
 
     for i in 1usize..=100usize {
-        let (mut vpa, test_string) = nested_recognizer(i * 100);
+        let (vpa, test_string) = nested_recognizer(i * 100);
         group.bench_function(BenchmarkId::from_parameter(i * 100), |b| {
             b.iter(|| vpa.recognize(black_box(&test_string)))
         });
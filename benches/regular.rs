@@ -34,7 +34,7 @@ fn bench_regular_translator(c: &mut Criterion) {
     let mut group = c.benchmark_group("Regular words/translator");
 
     for i in 1usize..=100usize {
-        let (mut vpa, test_string) = regular_translator(i * 100);
+        let (vpa, test_string) = regular_translator(i * 100);
         group.bench_function(BenchmarkId::from_parameter(i * 100), |b| {
             b.iter(|| vpa.translate(black_box(&test_string)))
         });
@@ -46,7 +46,7 @@ fn bench_regular_recognizer(c: &mut Criterion) {
     let mut group = c.benchmark_group("Regular words/recognizer");
 
     for i in 1usize..=100usize {
-        let (mut vpa, test_string) = regular_recognizer(i * 100);
+        let (vpa, test_string) = regular_recognizer(i * 100);
         group.bench_function(BenchmarkId::from_parameter(i * 100), |b| {
             b.iter(|| vpa.recognize(black_box(&test_string)))
         });
@@ -23,6 +23,13 @@ pub struct RuleTransform {
 pub enum RuleTransformItem {
     String(String),
     Identifier(String),
+    Function(Function),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    pub name: String,
+    pub arguments: Vec<RuleTransformItem>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -35,6 +42,23 @@ pub enum RuleSourceItem {
     RegexString(String),
     Identifier(Identifier),
     Nested(Nested),
+    Repetition(Repetition),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repetition {
+    pub item: Box<RuleSourceItem>,
+    pub kind: RepetitionKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepetitionKind {
+    /// `*`
+    ZeroOrMore,
+    /// `+`
+    OneOrMore,
+    /// `?`
+    Optional,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -0,0 +1,334 @@
+//! The `vpl` command-line tool, to check grammars and to run their recognizers and translators.
+//!
+//! The exit code is 0 on success, 1 if an input is rejected, 2 if the grammar is invalid, and 3 on a usage or I/O
+//! error, so the tool can be used in shell pipelines.
+
+use std::{
+    fs,
+    io::{self, Read, Write},
+    process::ExitCode,
+};
+
+use tree::Format;
+use vpl_parser_generator::{FunctionRegistry, Recognizer, Translator};
+
+mod repl;
+mod tree;
+
+const USAGE: &str = "\
+Usage: vpl <command> <grammar> [options] [input...]
+
+Commands:
+  check <grammar>               Parse and elaborate the grammar, and print its errors
+  recognize <grammar> [input...]
+                                Recognize every input, and print whether it is accepted
+  parse <grammar> [input...]    Print the parse tree of every input
+  translate <grammar> [input...]
+                                Print the translation of every input
+  dot <grammar>                 Print the automaton of the grammar in the DOT language
+  coverage <grammar> [input...] Parse every input, and print the rules that are never used
+  words <grammar>               Print the shortest inputs the grammar accepts, with their parse trees
+  repl <grammar>                Type inputs interactively, reloading the grammar when it changes
+
+Options:
+  --format json|sexp|tree       The format of the parse tree (default: tree)
+  --text <text>                 Use the text as input, instead of a file
+  --deny-warnings               Fail `check` if the grammar has warnings
+  --word <name>                 The word of `words` (default: the first word of the grammar)
+  --limit <n>                   The number of inputs of `words` (default: 10)
+  --rejected <n>                Stop `words` after n inputs the recognizer rejects (default: 100)
+  --threshold <n>               Print the states `coverage` used at most n times (default: 0)
+  --lcov <path>                 Write the coverage of the rules to a file in the lcov format
+  -h, --help                    Print this help
+
+An input is the path of a file, or `-` for standard input. Without inputs, standard input is read.
+
+Exit codes: 0 on success, 1 if an input is rejected, 2 if the grammar is invalid, 3 on a usage or I/O error.";
+
+/// The reason the tool failed, which determines the exit code.
+enum Failure {
+    Rejected,
+    Grammar(String),
+    Usage(String),
+    Io(String),
+}
+
+impl Failure {
+    fn exit_code(&self) -> u8 {
+        match self {
+            Failure::Rejected => 1,
+            Failure::Grammar(_) => 2,
+            Failure::Usage(_) | Failure::Io(_) => 3,
+        }
+    }
+}
+
+/// An input text, with the name it is reported by.
+struct Input {
+    name: String,
+    text: String,
+}
+
+struct Options {
+    command: String,
+    grammar: String,
+    format: Format,
+    deny_warnings: bool,
+    word: Option<String>,
+    limit: usize,
+    rejected: usize,
+    threshold: usize,
+    lcov: Option<String>,
+    inputs: Vec<Input>,
+}
+
+fn main() -> ExitCode {
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    if arguments.is_empty() || arguments.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{USAGE}");
+        return if arguments.is_empty() {
+            ExitCode::from(3)
+        } else {
+            ExitCode::SUCCESS
+        };
+    }
+    match parse_arguments(arguments).and_then(|options| run(&options)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(failure) => {
+            match &failure {
+                Failure::Rejected => {}
+                Failure::Grammar(message) => eprintln!("{message}"),
+                Failure::Usage(message) => eprintln!("{message}\n\n{USAGE}"),
+                Failure::Io(message) => eprintln!("{message}"),
+            }
+            ExitCode::from(failure.exit_code())
+        }
+    }
+}
+
+fn parse_arguments(arguments: Vec<String>) -> Result<Options, Failure> {
+    let mut arguments = arguments.into_iter();
+    let command = arguments.next().unwrap_or_default();
+    if !["check", "recognize", "parse", "translate", "coverage", "dot", "words", "repl"].contains(&command.as_str()) {
+        return Err(Failure::Usage(format!("Unknown command `{command}`")));
+    }
+    let mut grammar = None;
+    let mut format = Format::Tree;
+    let mut deny_warnings = false;
+    let mut word = None;
+    let mut limit = 10;
+    let mut rejected = 100;
+    let mut threshold = 0;
+    let mut lcov = None;
+    let mut inputs = Vec::new();
+    let mut paths = Vec::new();
+    while let Some(argument) = arguments.next() {
+        let (flag, value) = match argument.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+            _ => (argument.clone(), None),
+        };
+        let mut value = |name: &str| {
+            value
+                .clone()
+                .or_else(|| arguments.next())
+                .ok_or_else(|| Failure::Usage(format!("Missing value of {name}")))
+        };
+        match flag.as_str() {
+            "--deny-warnings" => deny_warnings = true,
+            "--format" => {
+                format = match value("--format")?.as_str() {
+                    "json" => Format::Json,
+                    "sexp" => Format::Sexp,
+                    "tree" => Format::Tree,
+                    other => return Err(Failure::Usage(format!("Unknown format `{other}`"))),
+                }
+            }
+            "--word" => word = Some(value("--word")?),
+            "--limit" => limit = number("--limit", value("--limit")?)?,
+            "--rejected" => rejected = number("--rejected", value("--rejected")?)?,
+            "--threshold" => threshold = number("--threshold", value("--threshold")?)?,
+            "--lcov" => lcov = Some(value("--lcov")?),
+            "--text" => inputs.push(Input {
+                name: "<text>".to_string(),
+                text: value("--text")?,
+            }),
+            _ if flag.starts_with("--") => {
+                return Err(Failure::Usage(format!("Unknown option `{flag}`")));
+            }
+            _ if grammar.is_none() => grammar = Some(argument),
+            _ => paths.push(argument),
+        }
+    }
+    let grammar = grammar.ok_or_else(|| Failure::Usage(format!("Missing grammar of `{command}`")))?;
+    let takes_input = !matches!(command.as_str(), "check" | "dot" | "words" | "repl");
+    if !takes_input && (!paths.is_empty() || !inputs.is_empty()) {
+        return Err(Failure::Usage(format!("`{command}` does not take inputs")));
+    }
+    if takes_input && paths.is_empty() && inputs.is_empty() {
+        paths.push("-".to_string());
+    }
+    for path in paths {
+        inputs.push(read_input(&path)?);
+    }
+    Ok(Options {
+        command,
+        grammar,
+        format,
+        deny_warnings,
+        word,
+        limit,
+        rejected,
+        threshold,
+        lcov,
+        inputs,
+    })
+}
+
+fn number(name: &str, text: String) -> Result<usize, Failure> {
+    text.parse()
+        .map_err(|_| Failure::Usage(format!("Invalid value `{text}` of {name}")))
+}
+
+fn read_input(path: &str) -> Result<Input, Failure> {
+    let text = if path == "-" {
+        let mut text = String::new();
+        io::stdin()
+            .read_to_string(&mut text)
+            .map_err(|e| Failure::Io(format!("Could not read standard input: {e}")))?;
+        text
+    } else {
+        fs::read_to_string(path).map_err(|e| Failure::Io(format!("Could not read {path}: {e}")))?
+    };
+    let name = if path == "-" { "<stdin>" } else { path };
+    Ok(Input {
+        name: name.to_string(),
+        text,
+    })
+}
+
+fn run(options: &Options) -> Result<(), Failure> {
+    let path = &options.grammar;
+    if options.command == "repl" {
+        return repl::run(path, options.format);
+    }
+    let grammar =
+        fs::read_to_string(path).map_err(|e| Failure::Io(format!("Could not read {path}: {e}")))?;
+    let grammar_error = |e: Box<dyn std::error::Error + '_>| Failure::Grammar(format!("{path}: {e}"));
+    let mut stdout = io::stdout().lock();
+    let mut rejected = false;
+    match options.command.as_str() {
+        "check" => {
+            let (warnings, overlaps) = vpl_parser_generator::check_all(&grammar, &FunctionRegistry::default())
+                .map_err(|e| grammar_error(e.into()))?;
+            let warnings: Vec<String> = warnings
+                .iter()
+                .map(ToString::to_string)
+                .chain(overlaps.iter().map(ToString::to_string))
+                .collect();
+            for warning in &warnings {
+                eprintln!("{path}: warning: {warning}");
+            }
+            if options.deny_warnings && !warnings.is_empty() {
+                return Err(Failure::Grammar(format!("{path}: warnings are denied")));
+            }
+            writeln!(stdout, "{path}: ok").map_err(write_error)?;
+        }
+        "dot" => {
+            let recognizer = Recognizer::new(&grammar).map_err(grammar_error)?;
+            write!(stdout, "{}", recognizer.to_dot()).map_err(write_error)?;
+        }
+        "words" => {
+            let recognizer = Recognizer::new(&grammar).map_err(grammar_error)?;
+            let word = match &options.word {
+                Some(word) => word.as_str(),
+                None => &recognizer.nonterminals()[0],
+            };
+            let words = recognizer
+                .shortest_words(word, options.limit, options.rejected)
+                .map_err(|e| Failure::Grammar(format!("{path}: {e}")))?;
+            for (input, tree) in words {
+                writeln!(stdout, "{input:?}").map_err(write_error)?;
+                let output = tree::format(&tree.borrow(), recognizer.nonterminals(), options.format);
+                write!(stdout, "{output}").map_err(write_error)?;
+            }
+        }
+        "recognize" => {
+            let recognizer = Recognizer::new(&grammar).map_err(grammar_error)?;
+            for input in &options.inputs {
+                let verdict = match recognizer.rejected_at(&input.text) {
+                    None => "accepted".to_string(),
+                    Some(offset) => {
+                        rejected = true;
+                        format!("rejected at {}", location(&input.text, offset))
+                    }
+                };
+                writeln!(stdout, "{}: {verdict}", input.name).map_err(write_error)?;
+            }
+        }
+        "parse" => {
+            let recognizer = Recognizer::new(&grammar).map_err(grammar_error)?;
+            for input in &options.inputs {
+                let Some((tree, _)) = recognizer.parse(&input.text) else {
+                    report_rejection(&recognizer, input);
+                    rejected = true;
+                    continue;
+                };
+                let output = tree::format(&tree.borrow(), recognizer.nonterminals(), options.format);
+                write!(stdout, "{output}").map_err(write_error)?;
+            }
+        }
+        "coverage" => {
+            let mut recognizer = Recognizer::new(&grammar).map_err(grammar_error)?;
+            recognizer.record_coverage();
+            for input in &options.inputs {
+                // Every input is only run once, so it is counted once.
+                if let Some(offset) = recognizer.rejected_at(&input.text) {
+                    eprintln!("{}: rejected at {}", input.name, location(&input.text, offset));
+                    rejected = true;
+                }
+            }
+            let coverage = recognizer.take_coverage().expect("The coverage is recorded");
+            write!(stdout, "{}", coverage.report(options.threshold)).map_err(write_error)?;
+            if let Some(lcov) = &options.lcov {
+                fs::write(lcov, coverage.to_lcov(path, &grammar))
+                    .map_err(|e| Failure::Io(format!("Could not write {lcov}: {e}")))?;
+            }
+        }
+        "translate" => {
+            let translator = Translator::new(&grammar).map_err(grammar_error)?;
+            for input in &options.inputs {
+                match translator.translate(&input.text) {
+                    Some(translation) => write!(stdout, "{translation}").map_err(write_error)?,
+                    None => {
+                        report_rejection(translator.recognizer(), input);
+                        rejected = true;
+                    }
+                }
+            }
+        }
+        _ => unreachable!("The command is checked by parse_arguments"),
+    }
+    stdout.flush().map_err(write_error)?;
+    if rejected {
+        Err(Failure::Rejected)
+    } else {
+        Ok(())
+    }
+}
+
+fn write_error(e: io::Error) -> Failure {
+    Failure::Io(format!("Could not write standard output: {e}"))
+}
+
+fn report_rejection(recognizer: &Recognizer, input: &Input) {
+    let offset = recognizer.rejected_at(&input.text).unwrap_or(input.text.len());
+    eprintln!("{}: rejected at {}", input.name, location(&input.text, offset));
+}
+
+/// Gives the line and column of a byte offset in a text, both counted from 1.
+fn location(text: &str, offset: usize) -> String {
+    let before = &text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    format!("line {line}, column {column}")
+}
@@ -0,0 +1,162 @@
+//! The interactive mode of `vpl repl`, which reloads the grammar when its file changes.
+
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use vpl_parser_generator::Translator;
+
+use crate::{location, tree, Failure, Format};
+
+const HELP: &str = "\
+Type an input to recognize, parse and translate it. A line ending with `\\` continues on the next line.
+
+Commands:
+  :format json|sexp|tree   Set the format of the parse tree
+  :reload                  Read the grammar again
+  :help                    Print this help
+  :quit                    Quit (or end of input)";
+
+/// How often the file of the grammar is checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Builds the translator of the grammar, whose recognizer parses the inputs.
+fn build(text: &str) -> Result<Translator, String> {
+    Translator::new(text).map_err(|e| e.to_string())
+}
+
+/// Runs the REPL until the end of standard input.
+///
+/// A grammar that does not build is reported, and the previous grammar is kept until the file is fixed.
+pub fn run(path: &str, mut format: Format) -> Result<(), Failure> {
+    let mut grammar = load(path, build(&read(path)?));
+    let changes = watch(path.to_string(), modified(path));
+    println!("Type :help for help.");
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        prompt("> ");
+        let Some(mut input) = read_line(&mut lines)? else {
+            println!();
+            return Ok(());
+        };
+        while let Some(line) = input.strip_suffix('\\') {
+            input = line.to_string();
+            prompt("| ");
+            match read_line(&mut lines)? {
+                Some(line) => {
+                    input.push('\n');
+                    input.push_str(&line);
+                }
+                None => break,
+            }
+        }
+        for built in changes.try_iter() {
+            match built {
+                Ok(translator) => {
+                    println!("Reloaded {path}");
+                    grammar = Some(translator);
+                }
+                Err(e) => println!("{path}: {e}\nThe last grammar that built is kept"),
+            }
+        }
+        match input.trim().split_once(' ').unwrap_or((input.trim(), "")) {
+            (":quit" | ":q", _) => return Ok(()),
+            (":help" | ":h", _) => println!("{HELP}"),
+            (":reload" | ":r", _) => grammar = load(path, build(&read(path)?)).or(grammar),
+            (":format", name) => match name.trim() {
+                "json" => format = Format::Json,
+                "sexp" => format = Format::Sexp,
+                "tree" => format = Format::Tree,
+                other => println!("Unknown format `{other}`, expected json, sexp or tree"),
+            },
+            (command, _) if command.starts_with(':') => {
+                println!("Unknown command `{command}`, type :help for help");
+            }
+            _ => match &grammar {
+                Some(grammar) => evaluate(grammar, &input, format),
+                None => println!("No grammar is loaded, fix the errors in {path}"),
+            },
+        }
+    }
+}
+
+/// Prints whether the input is accepted, with its parse tree and translation, or where it is rejected.
+fn evaluate(translator: &Translator, input: &str, format: Format) {
+    let recognizer = translator.recognizer();
+    let Some((tree, _)) = recognizer.parse(input) else {
+        let offset = recognizer.rejected_at(input).unwrap_or(input.len());
+        println!("rejected at {}", location(input, offset));
+        let line_start = input[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = input[offset..].find('\n').map_or(input.len(), |i| offset + i);
+        let column = input[line_start..offset].chars().count();
+        println!("  {}", &input[line_start..line_end]);
+        println!("  {}^", " ".repeat(column));
+        return;
+    };
+    println!("accepted");
+    print!("{}", tree::format(&tree.borrow(), recognizer.nonterminals(), format));
+    if let Some(translation) = translator.translate(input) {
+        println!("translation: {translation}");
+    }
+}
+
+/// Reports whether the grammar is loaded.
+fn load(path: &str, built: Result<Translator, String>) -> Option<Translator> {
+    match built {
+        Ok(translator) => {
+            println!("Loaded {path}");
+            Some(translator)
+        }
+        Err(e) => {
+            println!("{path}: {e}");
+            None
+        }
+    }
+}
+
+/// Polls the file of the grammar in the background, and builds and sends it when it is modified.
+///
+/// The grammar is built on the background thread, so the REPL does not wait for it, and only reports the changes
+/// before it evaluates the next input. The REPL keeps the last grammar that built.
+fn watch(path: String, mut last_modified: Option<SystemTime>) -> Receiver<Result<Translator, String>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || loop {
+        thread::sleep(POLL_INTERVAL);
+        let modified = modified(&path);
+        if modified == last_modified {
+            continue;
+        }
+        last_modified = modified;
+        let Ok(text) = fs::read_to_string(&path) else {
+            continue;
+        };
+        if sender.send(build(&text)).is_err() {
+            return;
+        }
+    });
+    receiver
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+fn read(path: &str) -> Result<String, Failure> {
+    fs::read_to_string(path).map_err(|e| Failure::Io(format!("Could not read {path}: {e}")))
+}
+
+fn read_line(lines: &mut impl Iterator<Item = io::Result<String>>) -> Result<Option<String>, Failure> {
+    lines
+        .next()
+        .transpose()
+        .map_err(|e| Failure::Io(format!("Could not read standard input: {e}")))
+}
+
+fn prompt(prompt: &str) {
+    print!("{prompt}");
+    let _ = io::stdout().flush();
+}
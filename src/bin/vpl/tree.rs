@@ -0,0 +1,94 @@
+//! The formats of the parse tree that are printed by `vpl parse` and the REPL.
+
+use std::fmt::Write;
+
+use serde_json::{json, Value};
+use vpl_parser_generator::{Child, Node};
+
+/// A format of the parse tree.
+#[derive(Clone, Copy)]
+pub enum Format {
+    Json,
+    Sexp,
+    Tree,
+}
+
+/// Gives the parse tree of a node in a format, ending with a newline.
+pub fn format(node: &Node, names: &[String], format: Format) -> String {
+    match format {
+        Format::Json => format!("{:#}\n", node_json(node, names)),
+        Format::Sexp => format!("{}\n", node_sexp(node, names)),
+        Format::Tree => {
+            let mut output = String::new();
+            node_tree(node, names, "", &mut output);
+            output
+        }
+    }
+}
+
+fn name(node: &Node, names: &[String]) -> String {
+    names
+        .get(node.identifier)
+        .cloned()
+        .unwrap_or_else(|| node.identifier.to_string())
+}
+
+/// Gives a node as a JSON object, with its nonterminal, rule (counted from 1) and children.
+fn node_json(node: &Node, names: &[String]) -> Value {
+    fn child_json(child: &Child, names: &[String]) -> Value {
+        match child {
+            Child::Node(node) => node_json(&node.borrow(), names),
+            Child::Leaf(leaf) => Value::String(leaf.clone()),
+            Child::List(list) => Value::Array(list.iter().map(|c| child_json(c, names)).collect()),
+        }
+    }
+    let children: Vec<Value> = node.children.iter().map(|c| child_json(c, names)).collect();
+    json!({
+        "nonterminal": name(node, names),
+        "rule": node.rule_nr + 1,
+        "children": children,
+    })
+}
+
+/// Gives a node as an S-expression `(Name:rule child...)`, where a leaf is a string and a list is `[child...]`.
+fn node_sexp(node: &Node, names: &[String]) -> String {
+    fn child_sexp(child: &Child, names: &[String]) -> String {
+        match child {
+            Child::Node(node) => node_sexp(&node.borrow(), names),
+            Child::Leaf(leaf) => Value::String(leaf.clone()).to_string(),
+            Child::List(list) => {
+                let items: Vec<String> = list.iter().map(|c| child_sexp(c, names)).collect();
+                format!("[{}]", items.join(" "))
+            }
+        }
+    }
+    let mut sexp = format!("({}:{}", name(node, names), node.rule_nr + 1);
+    for child in &node.children {
+        sexp.push(' ');
+        sexp.push_str(&child_sexp(child, names));
+    }
+    sexp.push(')');
+    sexp
+}
+
+/// Writes a node as an indented tree, with a line for every node, leaf and list.
+fn node_tree(node: &Node, names: &[String], indent: &str, output: &mut String) {
+    fn child_tree(child: &Child, names: &[String], indent: &str, output: &mut String) {
+        match child {
+            Child::Node(node) => node_tree(&node.borrow(), names, indent, output),
+            Child::Leaf(leaf) => {
+                let _ = writeln!(output, "{indent}{}", Value::String(leaf.clone()));
+            }
+            Child::List(list) => {
+                let _ = writeln!(output, "{indent}[{}]", list.len());
+                for child in list {
+                    child_tree(child, names, &format!("{indent}  "), output);
+                }
+            }
+        }
+    }
+    let _ = writeln!(output, "{indent}{} (rule {})", name(node, names), node.rule_nr + 1);
+    for child in &node.children {
+        child_tree(child, names, &format!("{indent}  "), output);
+    }
+}
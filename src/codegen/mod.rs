@@ -0,0 +1,242 @@
+#[doc(hidden)]
+pub mod nodes;
+#[doc(hidden)]
+pub mod tables;
+mod typed;
+
+use std::{env, error::Error, fmt::Write, fs, path::Path};
+
+pub use typed::generate_types;
+
+use crate::{
+    functions::FunctionRegistry,
+    ir::*,
+    recognizer::{Action, Recognizer},
+};
+
+/// The transforms of the rules of every nonterminal.
+type Transforms = Vec<Vec<RuleTransform>>;
+
+/// Generates a Rust module with the recognizer and translator of a grammar, with the built-in functions.
+///
+/// The grammar is parsed, checked and built into the tables of the recognizer while generating. The module is not a
+/// standalone parser, but these tables as data, which are run by the recognizer of this crate. Only the regular
+/// expressions are compiled when the module is used, the first time a recognizer or translator is asked for, and
+/// later calls reuse them. The module has the following functions:
+/// * `recognizer()` gives the recognizer of the grammar.
+/// * `translator()` gives the translator of the grammar, with the built-in functions.
+/// * `translator_with_functions(functions)` gives the translator with the given functions, or an error if a
+///   function of a transform is not registered.
+///
+/// The generated code is meant to be included in its own module, for example by a build script with [`build`].
+///
+/// # Examples
+///
+/// ```
+/// use vpl_parser_generator::codegen;
+///
+/// let code = codegen::generate("A:\n  \"a\" -> \"b\"").unwrap();
+/// assert!(code.contains("pub fn translator()"));
+/// assert!(codegen::generate("A:\n  A=A -> A").is_err());
+/// ```
+pub fn generate(grammar: &str) -> Result<String, Box<dyn Error + '_>> {
+    generate_with_functions(grammar, &FunctionRegistry::default())
+}
+
+/// Generates a Rust module with the recognizer and translator of a grammar, which can use the given functions.
+///
+/// The functions are only used to check the names and number of arguments of the functions in the transforms. The
+/// same functions are passed to `translator_with_functions` of the generated module. The `translator()` function
+/// is only generated if the grammar only uses built-in functions.
+pub fn generate_with_functions<'a>(
+    grammar: &'a str,
+    functions: &FunctionRegistry,
+) -> Result<String, Box<dyn Error + 'a>> {
+    let (recognizer, transforms) = build_grammar(grammar)?;
+    crate::elaborator::check_functions(&transforms, functions)?;
+    let tables = tables_code(&recognizer, &transforms)?;
+    Ok(write_module(&tables, built_in(&transforms))?)
+}
+
+/// Checks a grammar and gives the expression of its [`Tables`](tables::Tables), and whether the grammar only uses
+/// built-in functions. Other functions are not checked, as they are only known when the translator is created.
+///
+/// The expression uses `Action`, `Tables` and `Transform` of the `tables` module, which have to be in scope.
+#[doc(hidden)]
+pub fn generate_tables(grammar: &str) -> Result<(String, bool), Box<dyn Error + '_>> {
+    let (recognizer, transforms) = build_grammar(grammar)?;
+    Ok((tables_code(&recognizer, &transforms)?, built_in(&transforms)))
+}
+
+/// Parses and checks a grammar, and gives its recognizer and the transforms of its rules.
+fn build_grammar(grammar: &str) -> Result<(Recognizer, Transforms), Box<dyn Error + '_>> {
+    let grammar = crate::ast::Grammar::parse_complete(grammar)?;
+    let elaborated = crate::elaborator::elaborate(grammar)?;
+    let transforms = elaborated
+        .nonterminals
+        .iter()
+        .map(|w| w.rules.iter().map(|r| r.transform.clone()).collect())
+        .collect();
+    Ok((Recognizer::from(&elaborated), transforms))
+}
+
+fn built_in(transforms: &[Vec<RuleTransform>]) -> bool {
+    crate::elaborator::check_functions(transforms, &FunctionRegistry::default()).is_ok()
+}
+
+/// Generates the module of a grammar file to `{module}.rs` in the `OUT_DIR` of a build script.
+///
+/// Cargo is told to run the build script again when the grammar file changes. An invalid grammar gives an error,
+/// which fails the build if it is returned from the build script.
+///
+/// ```no_run
+/// // build.rs
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     vpl_parser_generator::codegen::build("grammar.vpa", "grammar")
+/// }
+/// ```
+///
+/// ```ignore
+/// // src/main.rs
+/// mod grammar {
+///     include!(concat!(env!("OUT_DIR"), "/grammar.rs"));
+/// }
+///
+/// fn main() {
+///     let translator = grammar::translator();
+///     println!("{:?}", translator.translate("input"));
+/// }
+/// ```
+pub fn build(grammar_path: impl AsRef<Path>, module: &str) -> Result<(), Box<dyn Error>> {
+    let grammar_path = grammar_path.as_ref();
+    println!("cargo:rerun-if-changed={}", grammar_path.display());
+    let grammar = fs::read_to_string(grammar_path)?;
+    let code = generate(&grammar)
+        .map_err(|error| format!("Grammar {} is invalid: {error}", grammar_path.display()))?;
+    let out_dir = env::var("OUT_DIR")?;
+    fs::write(Path::new(&out_dir).join(format!("{module}.rs")), code)?;
+    Ok(())
+}
+
+/// Generates the types of a grammar file with [`generate_types`] to `{module}.rs` in the `OUT_DIR` of a build
+/// script, like [`build`] does for the recognizer and translator.
+pub fn build_types(grammar_path: impl AsRef<Path>, module: &str) -> Result<(), Box<dyn Error>> {
+    let grammar_path = grammar_path.as_ref();
+    println!("cargo:rerun-if-changed={}", grammar_path.display());
+    let grammar = fs::read_to_string(grammar_path)?;
+    let code = generate_types(&grammar)
+        .map_err(|error| format!("Grammar {} is invalid: {error}", grammar_path.display()))?;
+    let out_dir = env::var("OUT_DIR")?;
+    fs::write(Path::new(&out_dir).join(format!("{module}.rs")), code)?;
+    Ok(())
+}
+
+fn write_module(tables: &str, built_in: bool) -> Result<String, std::fmt::Error> {
+    let mut code = String::new();
+    writeln!(code, "// Generated by vpl-parser-generator, do not edit.")?;
+    writeln!(code)?;
+    writeln!(code, "use ::vpl_parser_generator::codegen::tables::{{Action, Tables, Transform}};")?;
+    writeln!(code)?;
+    writeln!(code, "static TABLES: Tables = {tables};")?;
+    writeln!(code)?;
+    writeln!(code, "/// Gives the recognizer of the grammar.")?;
+    writeln!(code, "pub fn recognizer() -> ::vpl_parser_generator::Recognizer {{")?;
+    writeln!(code, "    TABLES.recognizer()")?;
+    writeln!(code, "}}")?;
+    if built_in {
+        writeln!(code)?;
+        writeln!(code, "/// Gives the translator of the grammar, with the built-in functions.")?;
+        writeln!(code, "pub fn translator() -> ::vpl_parser_generator::Translator {{")?;
+        writeln!(code, "    translator_with_functions(::std::default::Default::default())")?;
+        writeln!(code, "        .expect(\"The built-in functions are checked when the code is generated\")")?;
+        writeln!(code, "}}")?;
+    }
+    writeln!(code)?;
+    writeln!(code, "/// Gives the translator of the grammar, which uses the given functions in its transforms.")?;
+    writeln!(code, "pub fn translator_with_functions(")?;
+    writeln!(code, "    functions: ::vpl_parser_generator::FunctionRegistry,")?;
+    writeln!(code, ") -> ::std::result::Result<::vpl_parser_generator::Translator, ::std::string::String> {{")?;
+    writeln!(code, "    TABLES.translator(functions)")?;
+    writeln!(code, "}}")?;
+    Ok(code)
+}
+
+fn tables_code(
+    recognizer: &Recognizer,
+    transforms: &[Vec<RuleTransform>],
+) -> Result<String, std::fmt::Error> {
+    let mut code = String::new();
+    writeln!(code, "Tables {{")?;
+    writeln!(code, "    states: &[")?;
+    let mut states: Vec<_> = recognizer.next_state.iter().collect();
+    states.sort_by_key(|(state, _)| **state);
+    for (state, (regex, actions)) in states {
+        writeln!(code, "        ({state}, &[")?;
+        for (alternative, action) in regex.alternatives.iter().zip(actions) {
+            writeln!(code, "            ({alternative:?}, {}),", action_code(action))?;
+        }
+        writeln!(code, "        ]),")?;
+    }
+    writeln!(code, "    ],")?;
+    let mut exits: Vec<_> = recognizer.exits.iter().collect();
+    exits.sort();
+    let exits: Vec<String> = exits
+        .into_iter()
+        .map(|(point, next)| format!("({point}, {next})"))
+        .collect();
+    writeln!(code, "    exits: &[{}],", exits.join(", "))?;
+    writeln!(code, "    transforms: &[")?;
+    for rules in transforms {
+        writeln!(code, "        &[")?;
+        for transform in rules {
+            writeln!(code, "            &[{}],", transform_code(&transform.regs))?;
+        }
+        writeln!(code, "        ],")?;
+    }
+    writeln!(code, "    ],")?;
+    let names: Vec<String> = recognizer.names.iter().map(|name| format!("{name:?}")).collect();
+    writeln!(code, "    names: &[{}],", names.join(", "))?;
+    let mut rules: Vec<_> = recognizer.state_to_rule.iter().collect();
+    rules.sort();
+    let rules: Vec<String> = rules
+        .into_iter()
+        .map(|(state, (nonterminal, rule))| format!("({state}, {nonterminal}, {rule})"))
+        .collect();
+    writeln!(code, "    rules: &[{}],", rules.join(", "))?;
+    writeln!(code, "    compiled: ::std::sync::OnceLock::new(),")?;
+    write!(code, "}}")?;
+    Ok(code)
+}
+
+fn action_code(action: &Action) -> String {
+    match tables::Action::from(action) {
+        tables::Action::Call(point, nonterminal, captures, node) => {
+            format!("Action::Call({point}, {nonterminal}, {captures:?}, {node:?})")
+        }
+        tables::Action::Enter(point, nonterminal, node) => format!("Action::Enter({point}, {nonterminal}, {node:?})"),
+        tables::Action::Next(next, captures) => format!("Action::Next({next}, {captures:?})"),
+        tables::Action::Skip(next) => format!("Action::Skip({next})"),
+        tables::Action::Return(points) => {
+            let points: Vec<String> = points.iter().map(|point| format!("{point:?}")).collect();
+            format!("Action::Return(&[{}])", points.join(", "))
+        }
+    }
+}
+
+fn transform_code(regs: &[RuleTransformItem]) -> String {
+    regs.iter()
+        .map(|reg| match reg {
+            RuleTransformItem::String(s) => format!("Transform::String({s:?})"),
+            RuleTransformItem::Identifier(index) => format!("Transform::Identifier({index})"),
+            RuleTransformItem::Function(function) => format!(
+                "Transform::Function({:?}, &[{}])",
+                function.name,
+                transform_code(&function.arguments)
+            ),
+            RuleTransformItem::Join(index, separator) => {
+                format!("Transform::Join({index}, {separator:?})")
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
@@ -0,0 +1,54 @@
+//! Conversions of the parse tree to the types of [`generate_types`](super::generate_types).
+//!
+//! These functions are only public to be used by the generated code, and can change between versions of this crate.
+
+use crate::recognizer::{Child, Node};
+
+/// Gives the leaf at the index, or an empty string if the capture group did not participate.
+pub fn leaf(node: &Node, index: usize) -> String {
+    match node.children.get(index) {
+        Some(Child::Leaf(leaf)) => leaf.clone(),
+        _ => String::new(),
+    }
+}
+
+/// Gives the leaves of a repeated capture group.
+pub fn leaves(node: &Node, index: usize) -> Vec<String> {
+    match node.children.get(index) {
+        Some(Child::List(list)) => list
+            .iter()
+            .filter_map(|child| match child {
+                Child::Leaf(leaf) => Some(leaf.clone()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Converts the node at the index, which is always there after a successful parse.
+pub fn child<T>(node: &Node, index: usize, convert: fn(&Node) -> T) -> Box<T> {
+    optional_child(node, index, convert).expect("The parse tree does not match the grammar")
+}
+
+/// Converts the node at the index, if the optional identifier was matched.
+pub fn optional_child<T>(node: &Node, index: usize, convert: fn(&Node) -> T) -> Option<Box<T>> {
+    match node.children.get(index) {
+        Some(Child::Node(child)) => Some(Box::new(convert(&child.borrow()))),
+        _ => None,
+    }
+}
+
+/// Converts the nodes of a repeated identifier.
+pub fn children<T>(node: &Node, index: usize, convert: fn(&Node) -> T) -> Vec<T> {
+    match node.children.get(index) {
+        Some(Child::List(list)) => list
+            .iter()
+            .filter_map(|child| match child {
+                Child::Node(child) => Some(convert(&child.borrow())),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
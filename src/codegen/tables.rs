@@ -0,0 +1,200 @@
+//! Tables of a generated recognizer and translator.
+//!
+//! These types are only public to be used by the code of [`generate`](super::generate), and can change between
+//! versions of this crate.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    functions::FunctionRegistry,
+    ir,
+    recognizer::{self, NextMap, Recognizer},
+    regex::RegexParser,
+    Translator,
+};
+
+/// Position of a child in a parse tree node, and whether it is repeated.
+pub type Slot = (usize, bool);
+
+/// The return points of a return action, with the state and slot they continue in.
+pub type ReturnPoints = [(usize, usize, Slot)];
+
+/// An action of the recognizer, see the actions of the recognizer automaton.
+///
+/// The return points are a static slice in generated code, and a vector in saved recognizers.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action<P = &'static ReturnPoints> {
+    Call(usize, usize, Slot, Slot),
+    Enter(usize, usize, Slot),
+    Next(usize, Slot),
+    Skip(usize),
+    Return(P),
+}
+
+/// An item of the transform of a rule.
+#[derive(Debug)]
+pub enum Transform {
+    String(&'static str),
+    Identifier(usize),
+    Function(&'static str, &'static [Transform]),
+    Join(usize, &'static str),
+}
+
+/// The states, exits and transforms of a grammar.
+#[derive(Debug)]
+pub struct Tables {
+    /// Every state, with the regular expressions and actions of its alternatives.
+    pub states: &'static [(usize, &'static [(&'static str, Action)])],
+    /// The return points of identifiers, with the state after the identifier.
+    pub exits: &'static [(usize, usize)],
+    /// The transforms of the rules of every nonterminal.
+    pub transforms: &'static [&'static [&'static [Transform]]],
+    /// The names of the nonterminals.
+    pub names: &'static [&'static str],
+    /// The nonterminal and the index of the rule of the states that are part of a rule.
+    pub rules: &'static [(usize, usize, usize)],
+    /// The recognizer with the compiled regular expressions, once it is built.
+    pub compiled: OnceLock<Recognizer>,
+}
+
+impl Tables {
+    /// Gives the recognizer, whose regular expressions are only compiled by the first call.
+    pub fn recognizer(&self) -> Recognizer {
+        self.compiled.get_or_init(|| self.build()).clone()
+    }
+
+    /// Builds the recognizer, which only compiles the regular expressions of the states.
+    fn build(&self) -> Recognizer {
+        let next_state: NextMap = self
+            .states
+            .iter()
+            .map(|(state, alternatives)| {
+                let regexes: Vec<String> =
+                    alternatives.iter().map(|(regex, _)| regex.to_string()).collect();
+                let actions = alternatives.iter().map(|(_, action)| action.into()).collect();
+                (*state, (RegexParser::from(regexes), actions))
+            })
+            .collect();
+        Recognizer {
+            next_state: Arc::new(next_state),
+            exits: Arc::new(self.exits.iter().copied().collect()),
+            names: Arc::new(self.names.iter().map(|name| name.to_string()).collect()),
+            state_to_rule: Arc::new(
+                self.rules
+                    .iter()
+                    .map(|(state, nonterminal, rule)| (*state, (*nonterminal, *rule)))
+                    .collect(),
+            ),
+            coverage: None,
+        }
+    }
+
+    /// Builds the translator, after checking that the functions of the transforms are registered.
+    pub fn translator(&self, functions: FunctionRegistry) -> Result<Translator, String> {
+        let transforms = self
+            .transforms
+            .iter()
+            .map(|rules| {
+                rules
+                    .iter()
+                    .map(|regs| ir::RuleTransform {
+                        regs: regs.iter().map(|reg| reg.into()).collect(),
+                    })
+                    .collect()
+            })
+            .collect();
+        Translator::from_parts(self.recognizer(), transforms, functions).map_err(|e| e.message)
+    }
+}
+
+fn slot((index, repeated): Slot) -> recognizer::Slot {
+    recognizer::Slot { index, repeated }
+}
+
+impl<P: AsRef<ReturnPoints>> From<&Action<P>> for recognizer::Action {
+    fn from(action: &Action<P>) -> Self {
+        match action {
+            Action::Call(point, nonterminal, captures, node) => {
+                Self::Call(*point, *nonterminal, slot(*captures), slot(*node))
+            }
+            Action::Enter(point, nonterminal, node) => Self::Enter(*point, *nonterminal, slot(*node)),
+            Action::Next(next, captures) => Self::Next(*next, slot(*captures)),
+            Action::Skip(next) => Self::Skip(*next),
+            Action::Return(points) => Self::Return(
+                points
+                    .as_ref()
+                    .iter()
+                    .map(|(point, next, captures)| (*point, (*next, slot(*captures))))
+                    .collect::<HashMap<_, _>>(),
+            ),
+        }
+    }
+}
+
+impl From<&recognizer::Action> for Action<Vec<(usize, usize, Slot)>> {
+    /// Gives the action with its return points sorted, so the same automaton always gives the same tables.
+    fn from(action: &recognizer::Action) -> Self {
+        let slot = |slot: &recognizer::Slot| (slot.index, slot.repeated);
+        match action {
+            recognizer::Action::Call(point, nonterminal, captures, node) => {
+                Self::Call(*point, *nonterminal, slot(captures), slot(node))
+            }
+            recognizer::Action::Enter(point, nonterminal, node) => Self::Enter(*point, *nonterminal, slot(node)),
+            recognizer::Action::Next(next, captures) => Self::Next(*next, slot(captures)),
+            recognizer::Action::Skip(next) => Self::Skip(*next),
+            recognizer::Action::Return(map) => {
+                let mut points: Vec<(usize, usize, Slot)> = map
+                    .iter()
+                    .map(|(point, (next, captures))| (*point, *next, slot(captures)))
+                    .collect();
+                points.sort();
+                Self::Return(points)
+            }
+        }
+    }
+}
+
+impl From<&Transform> for ir::RuleTransformItem {
+    fn from(transform: &Transform) -> Self {
+        match transform {
+            Transform::String(s) => Self::String(s.to_string()),
+            Transform::Identifier(index) => Self::Identifier(*index),
+            Transform::Function(name, arguments) => Self::Function(ir::Function {
+                name: name.to_string(),
+                arguments: arguments.iter().map(|argument| argument.into()).collect(),
+            }),
+            Transform::Join(index, separator) => Self::Join(*index, separator.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::OnceLock;
+
+    use super::{Action, Tables, Transform};
+
+    static TABLES: Tables = Tables {
+        states: &[(1, &[("a", Action::Next(0, (0, false)))])],
+        exits: &[],
+        transforms: &[&[&[Transform::String("b")]]],
+        names: &["A"],
+        rules: &[],
+        compiled: OnceLock::new(),
+    };
+
+    #[test]
+    fn regexes_are_compiled_once() {
+        assert!(TABLES.compiled.get().is_none());
+        assert!(TABLES.recognizer().recognize("a").is_some());
+        assert!(TABLES.compiled.get().is_some());
+        let translator = TABLES.translator(Default::default()).unwrap();
+        assert_eq!(translator.translate("a"), Some("b".to_string()));
+    }
+}
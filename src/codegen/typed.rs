@@ -0,0 +1,203 @@
+use std::{error::Error, fmt::Write};
+
+use crate::{ast, regex::capture_names};
+
+/// The keywords that can not be used as the name of a type or field, and those that can not even be raw identifiers.
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "do", "dyn", "else", "enum", "extern", "false",
+    "final", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "macro", "match", "mod", "move", "mut",
+    "override", "priv", "pub", "ref", "return", "static", "struct", "trait", "true", "try", "type", "typeof",
+    "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+const RESERVED: &[&str] = &["_", "crate", "self", "Self", "super"];
+/// The path of the conversions of the parse tree, which is written in full as a type can be named `nodes`.
+const NODES: &str = "::vpl_parser_generator::codegen::nodes";
+
+/// A named field of a rule, with its type and the conversion of its child in the parse tree.
+struct Field {
+    name: String,
+    kind: String,
+    conversion: String,
+}
+
+/// Generates a Rust module with a type for every nonterminal of a grammar, and a conversion from the parse tree.
+///
+/// Every nonterminal becomes an enum with the same name, which has a variant `Rule1`, `Rule2`, ... for each of its
+/// rules. The variants have a field for every capture group and identifier of the rule, named after the capture
+/// group or the name after the `=` of the identifier:
+/// * A capture group is a `String`, which is empty if the group did not participate in the match.
+/// * An identifier is a `Box` of the type of its nonterminal, or an `Option` of it if it is optional.
+/// * A capture group or identifier that is repeated by `*` or `+` is a `Vec` of these values.
+///
+/// A name that is a keyword is a raw identifier like `r#type`, and a name that can not be one, like `Self`, gets a
+/// `_` after it, and one that starts with a digit a `_` before it.
+///
+/// Every type has a function `from_node`, which converts a node of the parse tree of a [`Recognizer`](crate::Recognizer)
+/// of the same grammar. The function `parse` of the module parses a text to the type of the first nonterminal.
+///
+/// # Examples
+///
+/// ```
+/// use vpl_parser_generator::codegen;
+///
+/// let code = codegen::generate_types("Pair:\n  \"(?P<key>[a-z]+)=\" Value=V -> key V\nValue:\n  \"[0-9]+\" -> \"n\"").unwrap();
+/// assert!(code.contains("pub enum Pair"));
+/// assert!(code.contains("key: ::std::string::String"));
+/// assert!(code.contains("V: ::std::boxed::Box<Value>"));
+/// ```
+pub fn generate_types(grammar: &str) -> Result<String, Box<dyn Error + '_>> {
+    let grammar = crate::ast::Grammar::parse_complete(grammar)?;
+    crate::elaborator::elaborate(grammar.clone())?;
+    Ok(types_code(&grammar)?)
+}
+
+fn types_code(grammar: &ast::Grammar) -> Result<String, std::fmt::Error> {
+    let mut code = String::new();
+    writeln!(code, "// Generated by vpl-parser-generator, do not edit.")?;
+    for word in &grammar.words {
+        let name = &identifier(&word.identifier);
+        let rules: Vec<Vec<Field>> = word.rules.iter().map(|rule| fields(&rule.source)).collect();
+        writeln!(code)?;
+        writeln!(code, "/// The nonterminal `{name}` of the grammar.")?;
+        writeln!(code, "#[allow(non_camel_case_types, non_snake_case)]")?;
+        writeln!(code, "#[derive(Debug, Clone, PartialEq, Eq)]")?;
+        writeln!(code, "pub enum {name} {{")?;
+        for (rule_index, fields) in rules.iter().enumerate() {
+            if fields.is_empty() {
+                writeln!(code, "    Rule{},", rule_index + 1)?;
+                continue;
+            }
+            writeln!(code, "    Rule{} {{", rule_index + 1)?;
+            for field in fields {
+                writeln!(code, "        {}: {},", field.name, field.kind)?;
+            }
+            writeln!(code, "    }},")?;
+        }
+        writeln!(code, "}}")?;
+        writeln!(code)?;
+        writeln!(code, "impl {name} {{")?;
+        writeln!(code, "    /// Converts a node of `{name}` in the parse tree.")?;
+        writeln!(code, "    pub fn from_node(node: &::vpl_parser_generator::Node) -> Self {{")?;
+        writeln!(code, "        match node.rule_nr {{")?;
+        for (rule_index, fields) in rules.iter().enumerate() {
+            if fields.is_empty() {
+                writeln!(code, "            {rule_index} => Self::Rule{},", rule_index + 1)?;
+                continue;
+            }
+            writeln!(code, "            {rule_index} => Self::Rule{} {{", rule_index + 1)?;
+            for field in fields {
+                writeln!(code, "                {}: {},", field.name, field.conversion)?;
+            }
+            writeln!(code, "            }},")?;
+        }
+        writeln!(
+            code,
+            "            rule_nr => panic!(\"Rule {{}} is not a rule of {name}\", rule_nr + 1),"
+        )?;
+        writeln!(code, "        }}")?;
+        writeln!(code, "    }}")?;
+        writeln!(code, "}}")?;
+    }
+    if let Some(word) = grammar.words.first() {
+        let name = &identifier(&word.identifier);
+        writeln!(code)?;
+        writeln!(code, "/// Parses a text to `{name}`, or gives `None` if the text is not recognized.")?;
+        writeln!(code, "pub fn parse(")?;
+        writeln!(code, "    recognizer: &::vpl_parser_generator::Recognizer,")?;
+        writeln!(code, "    text: &str,")?;
+        writeln!(code, ") -> ::std::option::Option<{name}> {{")?;
+        writeln!(code, "    let (tree, _) = recognizer.parse(text)?;")?;
+        writeln!(code, "    let node = tree.borrow();")?;
+        writeln!(code, "    ::std::option::Option::Some({name}::from_node(&node))")?;
+        writeln!(code, "}}")?;
+    }
+    Ok(code)
+}
+
+/// Gives the fields of a rule, in the order of their position in the parse tree node.
+fn fields(source: &ast::RuleSource) -> Vec<Field> {
+    fn add_captures(regex: &str, repetition: Option<ast::RepetitionKind>, fields: &mut Vec<Field>) {
+        for name in capture_names(regex) {
+            let index = fields.len();
+            let (kind, conversion) = match repetition {
+                Some(ast::RepetitionKind::ZeroOrMore | ast::RepetitionKind::OneOrMore) => (
+                    "::std::vec::Vec<::std::string::String>".to_string(),
+                    format!("{NODES}::leaves(node, {index})"),
+                ),
+                _ => (
+                    "::std::string::String".to_string(),
+                    format!("{NODES}::leaf(node, {index})"),
+                ),
+            };
+            fields.push(Field {
+                name: identifier(&name),
+                kind,
+                conversion,
+            });
+        }
+    }
+    fn add_identifier(
+        identifier: &ast::Identifier,
+        repetition: Option<ast::RepetitionKind>,
+        fields: &mut Vec<Field>,
+    ) {
+        let index = fields.len();
+        let nonterminal = &self::identifier(&identifier.internal);
+        let (kind, conversion) = match repetition {
+            Some(ast::RepetitionKind::ZeroOrMore | ast::RepetitionKind::OneOrMore) => (
+                format!("::std::vec::Vec<{nonterminal}>"),
+                format!("{NODES}::children(node, {index}, {nonterminal}::from_node)"),
+            ),
+            Some(ast::RepetitionKind::Optional) => (
+                format!("::std::option::Option<::std::boxed::Box<{nonterminal}>>"),
+                format!("{NODES}::optional_child(node, {index}, {nonterminal}::from_node)"),
+            ),
+            None => (
+                format!("::std::boxed::Box<{nonterminal}>"),
+                format!("{NODES}::child(node, {index}, {nonterminal}::from_node)"),
+            ),
+        };
+        fields.push(Field {
+            name: self::identifier(&identifier.external),
+            kind,
+            conversion,
+        });
+    }
+    fn add_item(
+        reg: &ast::RuleSourceItem,
+        repetition: Option<ast::RepetitionKind>,
+        fields: &mut Vec<Field>,
+    ) {
+        match reg {
+            ast::RuleSourceItem::RegexString(s) => add_captures(s, repetition, fields),
+            ast::RuleSourceItem::Identifier(identifier) => {
+                add_identifier(identifier, repetition, fields)
+            }
+            ast::RuleSourceItem::Nested(nested) => {
+                add_captures(&nested.call_symbol, repetition, fields);
+                add_identifier(&nested.rule, repetition, fields);
+                add_captures(&nested.return_symbol, repetition, fields);
+            }
+            ast::RuleSourceItem::Repetition(r) => add_item(&r.item, Some(r.kind), fields),
+        }
+    }
+    let mut fields = Vec::new();
+    source
+        .regs
+        .iter()
+        .for_each(|reg| add_item(reg, None, &mut fields));
+    fields
+}
+
+/// Gives the Rust identifier of the name of a type or field, as a raw identifier if the name is a keyword.
+fn identifier(name: &str) -> String {
+    if name.starts_with(|c: char| c.is_numeric()) {
+        format!("_{name}")
+    } else if RESERVED.contains(&name) {
+        format!("{name}_")
+    } else if KEYWORDS.contains(&name) {
+        format!("r#{name}")
+    } else {
+        name.to_string()
+    }
+}
@@ -1,19 +1,245 @@
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
 
 use crate::ast;
+use crate::functions::{parse_width, FunctionRegistry};
 use crate::ir;
+use crate::regex::{capture_names, intersection, matches_empty};
 
-pub type ElaborationError = String;
+/// The part of a grammar that an [`ElaborationError`] is about, so an editor can show the error at its place.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ErrorLocation {
+    /// The line and column, both from 1, at which the grammar could not be parsed further.
+    Position(usize, usize),
+    /// The definition of a word.
+    Definition(String),
+    /// A use of a word in a rule, as `Word=name`.
+    Reference(String),
+    /// A use of the first word in a rule of the second word.
+    ReferenceIn(String, String),
+    /// The name a word or capture group is bound to in a rule source.
+    Binding(String),
+    /// A name in a rule transform.
+    Use(String),
+    /// A rule of a word, by its index from 0.
+    Rule(String, usize),
+    /// A regex, as it is written in the grammar.
+    Regex(String),
+    /// A function applied in a rule transform.
+    Function(String),
+}
+
+/// An error in a grammar, which stops it from being built, with the part of the grammar it is about.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ElaborationError {
+    pub message: String,
+    pub location: ErrorLocation,
+}
+
+impl ElaborationError {
+    pub(crate) fn new(location: ErrorLocation, message: String) -> Self {
+        Self { message, location }
+    }
+}
+
+impl fmt::Display for ElaborationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for ElaborationError {}
+
+pub(crate) type ElaborationResult<T> = Result<T, ElaborationError>;
+
+/// The kind of a [`Warning`] about a word of a grammar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WarningKind {
+    /// The word is not used by any other word, and is not the start word.
+    Unused,
+    /// The word is used, but only by words that can not be reached from the start word.
+    Unreachable,
+    /// Every rule of the word uses a word that can not match any finite input, possibly itself.
+    Unproductive,
+}
+
+/// A problem with a word of a grammar, which does not stop the grammar from being built.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Warning {
+    pub kind: WarningKind,
+    pub word: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            WarningKind::Unused => write!(f, "Word {} is never used", self.word),
+            WarningKind::Unreachable => write!(f, "Word {} can not be reached from the start word", self.word),
+            WarningKind::Unproductive => write!(f, "Word {} can not match any finite input", self.word),
+        }
+    }
+}
+
+/// Parses and checks a grammar, and gives its warnings.
+///
+/// A grammar with errors gives the same error as [`Recognizer::new`](crate::Recognizer::new). The warnings are about
+/// words that are unused, that can not be reached from the start word (the first word), or that can not match any
+/// finite input. They are in the order of the words in the grammar, so a caller can report them, or refuse the
+/// grammar if there are any.
+///
+/// # Examples
+///
+/// ```
+/// use vpl_parser_generator::{check, WarningKind};
+///
+/// let grammar = "A:\n  \"a\" -> \"a\"\nB:\n  C=C -> C\nC:\n  [\"\\(\" C=C \"\\)\"] -> C";
+/// let warnings = check(grammar).unwrap();
+/// let kinds: Vec<_> = warnings.iter().map(|w| (w.word.as_str(), w.kind)).collect();
+/// assert_eq!(kinds, [
+///     ("B", WarningKind::Unused),
+///     ("B", WarningKind::Unproductive),
+///     ("C", WarningKind::Unreachable),
+///     ("C", WarningKind::Unproductive),
+/// ]);
+/// assert_eq!(warnings[0].to_string(), "Word B is never used");
+/// assert!(check("A:\n  B=B -> B").is_err());
+/// ```
+pub fn check(grammar: &str) -> Result<Vec<Warning>, Box<dyn Error + '_>> {
+    let grammar = ast::Grammar::parse_complete(grammar)?;
+    elaborate(grammar.clone())?;
+    Ok(warnings(&grammar))
+}
+
+/// The kind of a symbol of the alphabet of a visibly pushdown grammar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SymbolKind {
+    /// The call symbol of a nested block, which pushes on the stack.
+    Call,
+    /// The return symbol of a nested block, which pops from the stack.
+    Return,
+    /// A regex of a rule outside nested blocks, which leaves the stack alone.
+    Internal,
+}
+
+impl fmt::Display for SymbolKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SymbolKind::Call => write!(f, "call"),
+            SymbolKind::Return => write!(f, "return"),
+            SymbolKind::Internal => write!(f, "internal"),
+        }
+    }
+}
+
+/// A regex of a grammar, with its kind of symbol and the first word that uses it as that kind.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Symbol {
+    pub kind: SymbolKind,
+    pub regex: String,
+    pub word: String,
+}
+
+/// Two symbols of different kinds that match the same input, which is given as a witness.
+///
+/// The alphabet of a visibly pushdown grammar is partitioned in call, return and internal symbols, so the stack
+/// only depends on the input. A grammar with overlapping symbols is still built, as the recognizer takes the first
+/// symbol that matches, but the language it recognizes depends on the order of the rules.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SymbolOverlap {
+    pub first: Symbol,
+    pub second: Symbol,
+    pub witness: String,
+}
+
+impl fmt::Display for SymbolOverlap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (first, second) = (&self.first, &self.second);
+        let kind = first.kind.to_string();
+        let kind = kind[..1].to_uppercase() + &kind[1..];
+        write!(
+            f,
+            "{kind} symbol \"{}\" of word {} and {} symbol \"{}\" of word {} both match {:?}",
+            first.regex, first.word, second.kind, second.regex, second.word, self.witness
+        )
+    }
+}
+
+/// Parses and checks a grammar, and gives the pairs of call, return and internal symbols that overlap.
+///
+/// The language of every regex of the grammar is intersected with those of the other kinds, and a shortest input
+/// in both is given as witness. An empty result means that the alphabet of the grammar is truly partitioned, so a
+/// caller that only accepts visibly pushdown grammars can refuse any other result.
+///
+/// # Examples
+///
+/// ```
+/// use vpl_parser_generator::{check_alphabet, SymbolKind};
+///
+/// let grammar = "A:\n  [\"\\(\" B=B \"\\)\"] -> B\nB:\n  \"[a-z(]+\" -> \"b\"";
+/// let overlaps = check_alphabet(grammar).unwrap();
+/// assert_eq!(overlaps.len(), 1);
+/// assert_eq!(overlaps[0].first.kind, SymbolKind::Call);
+/// assert_eq!(overlaps[0].second.kind, SymbolKind::Internal);
+/// assert_eq!(overlaps[0].witness, "(");
+/// assert_eq!(
+///     overlaps[0].to_string(),
+///     r#"Call symbol "\(" of word A and internal symbol "[a-z(]+" of word B both match "(""#
+/// );
+/// ```
+pub fn check_alphabet(grammar: &str) -> Result<Vec<SymbolOverlap>, Box<dyn Error + '_>> {
+    let grammar = ast::Grammar::parse_complete(grammar)?;
+    elaborate(grammar.clone())?;
+    Ok(symbol_overlaps(&grammar))
+}
 
-type ElaborationResult<T> = Result<T, ElaborationError>;
+/// Parses and checks a grammar once, and gives both its warnings, like [`check`], and the pairs of symbols that
+/// overlap, like [`check_alphabet`].
+///
+/// The functions of the transforms are also checked, so a grammar with errors gives the same error as
+/// [`Translator::with_functions`](crate::Translator::with_functions).
+///
+/// # Examples
+///
+/// ```
+/// use vpl_parser_generator::{check_all, ErrorLocation, FunctionRegistry, WarningKind};
+///
+/// let grammar = "A:\n  [\"\\(\" A=A \"\\)\"] -> A\n  \"[a-z(]+\" -> upper(\"b\")\nB:\n  \"b\" -> \"b\"";
+/// let (warnings, overlaps) = check_all(grammar, &FunctionRegistry::default()).unwrap();
+/// assert_eq!(warnings.len(), 1);
+/// assert_eq!(warnings[0].kind, WarningKind::Unused);
+/// assert_eq!(overlaps.len(), 1);
+/// let error = check_all(grammar, &FunctionRegistry::new()).unwrap_err();
+/// assert_eq!(error.location, ErrorLocation::Function("upper".to_string()));
+/// ```
+pub fn check_all(
+    grammar: &str,
+    functions: &FunctionRegistry,
+) -> Result<(Vec<Warning>, Vec<SymbolOverlap>), ElaborationError> {
+    let grammar = ast::Grammar::parse_complete(grammar)?;
+    let elaborated = elaborate(grammar.clone())?;
+    let transforms: Vec<Vec<ir::RuleTransform>> = elaborated
+        .nonterminals
+        .iter()
+        .map(|word| word.rules.iter().map(|rule| rule.transform.clone()).collect())
+        .collect();
+    check_functions(&transforms, functions)?;
+    Ok((warnings(&grammar), symbol_overlaps(&grammar)))
+}
 
 pub fn elaborate(grammar: ast::Grammar) -> ElaborationResult<ir::Grammar> {
     // Check if all used words are defined
     let grammar = check_definitions(grammar)?;
 
-    // Check if all rules are nested or only use subsequent rules
-    let grammar = check_rule_order(grammar)?;
+    // Check if all call and return symbols consume input
+    let grammar = check_nested_symbols(grammar)?;
+
+    // Check if no word can use itself without consuming input
+    let grammar = check_left_recursion(grammar)?;
+
+    // Check if repeated words always consume input
+    let grammar = check_repetitions(grammar)?;
 
     Ok(ir::Grammar::from(&grammar))
 }
@@ -29,19 +255,16 @@ fn convert_to_hashmap(grammar: &ast::Grammar) -> HashMap<String, (usize, &ast::N
 fn check_definitions(grammar: ast::Grammar) -> ElaborationResult<ast::Grammar> {
     let words: HashMap<String, (usize, &ast::Nonterminal)> = convert_to_hashmap(&grammar);
     for word in &grammar.words {
-        for rule in &word.rules {
-            let mut extern_identifiers = rule
+        for (rule_index, rule) in word.rules.iter().enumerate() {
+            let mut extern_identifiers: Vec<String> = rule
                 .transform
                 .regs
                 .iter()
-                .filter_map(|reg| {
-                    if let ast::RuleTransformItem::Identifier(id) = reg {
-                        Some(id.clone())
-                    } else {
-                        None
-                    }
+                .filter_map(|reg| match reg {
+                    ast::RuleTransformItem::Identifier(id) => Some(id.clone()),
+                    _ => None,
                 })
-                .collect::<Vec<String>>();
+                .collect();
             if extern_identifiers
                 .iter()
                 .cloned()
@@ -49,50 +272,29 @@ fn check_definitions(grammar: ast::Grammar) -> ElaborationResult<ast::Grammar> {
                 .len()
                 != extern_identifiers.len()
             {
-                return Err(format!(
-                    "Word {} has a rule with two identicial identifiers in the transform",
-                    word.identifier
+                return Err(ElaborationError::new(
+                    ErrorLocation::Rule(word.identifier.clone(), rule_index),
+                    format!(
+                        "Word {} has a rule with two identical identifiers in the transform, outside of function \
+                         arguments",
+                        word.identifier
+                    ),
                 ));
             }
-            for reg in &rule.source.regs {
-                match reg {
-                    ast::RuleSourceItem::Identifier(id) => {
-                        if !words.contains_key(&id.internal) {
-                            return Err(format!("Word {} is not defined", id.internal));
-                        }
-                        if extern_identifiers.contains(&id.external) {
-                            extern_identifiers.retain(|x| x != &id.external);
-                        } else {
-                            return Err(format!(
-                                "Word {} is not used in the transform",
-                                id.external
-                            ));
-                        }
-                    }
-                    ast::RuleSourceItem::Nested(n) => {
-                        if !words.contains_key(&n.rule.internal) {
-                            return Err(format!("Word {} is not defined", &n.rule.internal));
-                        }
-                        if extern_identifiers.contains(&n.rule.external) {
-                            extern_identifiers.retain(|x| x != &n.rule.external);
-                        } else {
-                            return Err(format!(
-                                "Word {} is not used in the transform",
-                                n.rule.external
-                            ));
-                        }
-                        check_regex(&n.call_symbol, &mut extern_identifiers)?;
-                        check_regex(&n.return_symbol, &mut extern_identifiers)?;
-                    }
-                    ast::RuleSourceItem::RegexString(s) => {
-                        check_regex(s, &mut extern_identifiers)?;
-                    }
+            // The arguments of functions can read an identifier more than once, like `html(v) " " upper(v)`.
+            for id in transform_identifiers(&rule.transform.regs) {
+                if !extern_identifiers.contains(&id) {
+                    extern_identifiers.push(id);
                 }
             }
+            for reg in &rule.source.regs {
+                check_item(reg, &words, &mut extern_identifiers)?;
+            }
+            check_joins(&rule.transform.regs, &rule.source)?;
             if !extern_identifiers.is_empty() {
-                return Err(format!(
-                    "Word {} is not used in the source",
-                    extern_identifiers[0]
+                return Err(ElaborationError::new(
+                    ErrorLocation::Use(extern_identifiers[0].clone()),
+                    format!("Word {} is not used in the source", extern_identifiers[0]),
                 ));
             }
         }
@@ -100,30 +302,338 @@ fn check_definitions(grammar: ast::Grammar) -> ElaborationResult<ast::Grammar> {
     Ok(grammar)
 }
 
-fn check_regex(s: &str, extern_identifiers: &mut Vec<String>) -> Result<(), String> {
-    let regex = regex::Regex::new(s).unwrap();
-    for name in regex.capture_names().flatten() {
-        if extern_identifiers.contains(&name.to_string()) {
-            extern_identifiers.retain(|x| x != &name.to_string());
+fn check_item(
+    reg: &ast::RuleSourceItem,
+    words: &HashMap<String, (usize, &ast::Nonterminal)>,
+    extern_identifiers: &mut Vec<String>,
+) -> ElaborationResult<()> {
+    match reg {
+        ast::RuleSourceItem::Identifier(id) => {
+            if !words.contains_key(&id.internal) {
+                return Err(ElaborationError::new(
+                    ErrorLocation::Reference(id.internal.clone()),
+                    format!("Word {} is not defined", id.internal),
+                ));
+            }
+            if extern_identifiers.contains(&id.external) {
+                extern_identifiers.retain(|x| x != &id.external);
+            } else {
+                return Err(ElaborationError::new(
+                    ErrorLocation::Binding(id.external.clone()),
+                    format!("Word {} is not used in the transform", id.external),
+                ));
+            }
+        }
+        ast::RuleSourceItem::Nested(n) => {
+            if !words.contains_key(&n.rule.internal) {
+                return Err(ElaborationError::new(
+                    ErrorLocation::Reference(n.rule.internal.clone()),
+                    format!("Word {} is not defined", n.rule.internal),
+                ));
+            }
+            if extern_identifiers.contains(&n.rule.external) {
+                extern_identifiers.retain(|x| x != &n.rule.external);
+            } else {
+                return Err(ElaborationError::new(
+                    ErrorLocation::Binding(n.rule.external.clone()),
+                    format!("Word {} is not used in the transform", n.rule.external),
+                ));
+            }
+            check_regex(&n.call_symbol, extern_identifiers)?;
+            check_regex(&n.return_symbol, extern_identifiers)?;
+        }
+        ast::RuleSourceItem::RegexString(s) => {
+            check_regex(s, extern_identifiers)?;
+        }
+        ast::RuleSourceItem::Repetition(repetition) => {
+            check_item(&repetition.item, words, extern_identifiers)?;
+        }
+    }
+    Ok(())
+}
+
+/// Gives the identifiers and capture groups that are bound inside a `*` or `+` repetition of the source.
+fn repeated_identifiers(source: &ast::RuleSource) -> Vec<String> {
+    fn bound(reg: &ast::RuleSourceItem) -> Vec<String> {
+        match reg {
+            ast::RuleSourceItem::Identifier(id) => vec![id.external.clone()],
+            ast::RuleSourceItem::Nested(n) => {
+                let mut names = capture_names(&n.call_symbol);
+                names.push(n.rule.external.clone());
+                names.extend(capture_names(&n.return_symbol));
+                names
+            }
+            ast::RuleSourceItem::RegexString(s) => capture_names(s),
+            ast::RuleSourceItem::Repetition(repetition) => bound(&repetition.item),
+        }
+    }
+    source
+        .regs
+        .iter()
+        .filter_map(|reg| match reg {
+            ast::RuleSourceItem::Repetition(repetition)
+                if repetition.kind != ast::RepetitionKind::Optional =>
+            {
+                Some(bound(&repetition.item))
+            }
+            _ => None,
+        })
+        .flatten()
+        .collect()
+}
+
+/// Checks that `join` is only applied to a repeated identifier and a separator string.
+fn check_joins(regs: &[ast::RuleTransformItem], source: &ast::RuleSource) -> ElaborationResult<()> {
+    for reg in regs {
+        if let ast::RuleTransformItem::Function(function) = reg {
+            if function.name != ir::JOIN {
+                check_joins(&function.arguments, source)?;
+                continue;
+            }
+            match &function.arguments[..] {
+                [ast::RuleTransformItem::Identifier(id), ast::RuleTransformItem::String(_)] => {
+                    if !repeated_identifiers(source).contains(id) {
+                        return Err(ElaborationError::new(
+                            ErrorLocation::Use(id.clone()),
+                            format!("Word {id} is joined, but is not repeated"),
+                        ));
+                    }
+                }
+                _ => {
+                    return Err(ElaborationError::new(
+                        ErrorLocation::Function(ir::JOIN.to_string()),
+                        format!("Function {} expects a repeated identifier and a separator string", ir::JOIN),
+                    ))
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Collects all identifiers used in the transform, including the arguments of functions.
+fn transform_identifiers(regs: &[ast::RuleTransformItem]) -> Vec<String> {
+    regs.iter()
+        .flat_map(|reg| match reg {
+            ast::RuleTransformItem::Identifier(id) => vec![id.clone()],
+            ast::RuleTransformItem::Function(function) => transform_identifiers(&function.arguments),
+            ast::RuleTransformItem::String(_) => vec![],
+        })
+        .collect()
+}
+
+/// Checks if all functions used in the transforms are registered and are given the right number of arguments.
+pub fn check_functions(
+    transforms: &[Vec<ir::RuleTransform>],
+    functions: &FunctionRegistry,
+) -> ElaborationResult<()> {
+    fn check(regs: &[ir::RuleTransformItem], functions: &FunctionRegistry) -> ElaborationResult<()> {
+        for reg in regs {
+            if let ir::RuleTransformItem::Function(function) = reg {
+                let error = |message| ElaborationError::new(ErrorLocation::Function(function.name.clone()), message);
+                let arity = functions
+                    .arity(&function.name)
+                    .ok_or_else(|| error(format!("Function {} is not defined", function.name)))?;
+                if arity != function.arguments.len() {
+                    return Err(error(format!(
+                        "Function {} expects {} arguments, but {} are given",
+                        function.name,
+                        arity,
+                        function.arguments.len()
+                    )));
+                }
+                if let (true, Some(ir::RuleTransformItem::String(width))) =
+                    (functions.takes_width(&function.name), function.arguments.last())
+                {
+                    if parse_width(width).is_none() {
+                        return Err(error(format!(
+                            "Function {} expects a width, but {width:?} is given",
+                            function.name
+                        )));
+                    }
+                }
+                check(&function.arguments, functions)?;
+            }
+        }
+        Ok(())
+    }
+    for transform in transforms.iter().flatten() {
+        check(&transform.regs, functions)?;
+    }
+    Ok(())
+}
+
+fn check_regex(s: &str, extern_identifiers: &mut Vec<String>) -> ElaborationResult<()> {
+    if let Err(error) = regex::Regex::new(s) {
+        return Err(ElaborationError::new(
+            ErrorLocation::Regex(s.to_string()),
+            format!("Regex {s} is invalid: {error}"),
+        ));
+    }
+    for name in capture_names(s) {
+        if extern_identifiers.contains(&name) {
+            extern_identifiers.retain(|x| x != &name);
         } else {
-            return Err(format!("Word {name} is not used in the transform"));
+            return Err(ElaborationError::new(
+                ErrorLocation::Binding(name.clone()),
+                format!("Word {name} is not used in the transform"),
+            ));
         }
     }
     Ok(())
 }
 
-fn check_rule_order(grammar: ast::Grammar) -> ElaborationResult<ast::Grammar> {
-    let words: HashMap<String, (usize, &ast::Nonterminal)> = convert_to_hashmap(&grammar);
-    for (index, word) in grammar.words.iter().enumerate() {
+/// Checks that the call and return symbols of nested blocks can not match the empty string.
+///
+/// These symbols change the stack of the recognizer, so they have to consume input to be visible in the input.
+fn check_nested_symbols(grammar: ast::Grammar) -> ElaborationResult<ast::Grammar> {
+    fn check(reg: &ast::RuleSourceItem, word: &str, rule_index: usize) -> ElaborationResult<()> {
+        match reg {
+            ast::RuleSourceItem::Nested(n) => {
+                for (kind, symbol) in [("call", &n.call_symbol), ("return", &n.return_symbol)] {
+                    if matches_empty(symbol) {
+                        return Err(ElaborationError::new(
+                            ErrorLocation::Rule(word.to_string(), rule_index),
+                            format!(
+                                "Rule {} of word {} has {} symbol \"{}\", which can match the empty string",
+                                rule_index + 1,
+                                word,
+                                kind,
+                                symbol
+                            ),
+                        ));
+                    }
+                }
+                Ok(())
+            }
+            ast::RuleSourceItem::Repetition(repetition) => check(&repetition.item, word, rule_index),
+            ast::RuleSourceItem::Identifier(_) | ast::RuleSourceItem::RegexString(_) => Ok(()),
+        }
+    }
+    for word in &grammar.words {
+        for (rule_index, rule) in word.rules.iter().enumerate() {
+            for reg in &rule.source.regs {
+                check(reg, &word.identifier, rule_index)?;
+            }
+        }
+    }
+    Ok(grammar)
+}
+
+/// Checks that no word can use itself again without consuming input, as the recognizer would enter it forever.
+///
+/// Recursion is allowed after an item that consumes input, like `List: "a" List=L` or a nested block.
+fn check_left_recursion(grammar: ast::Grammar) -> ElaborationResult<ast::Grammar> {
+    let nullable_words = nullable_words(&grammar);
+    // The words that can be entered by a word before any input is consumed.
+    let mut left_words: HashMap<&str, Vec<&str>> = HashMap::new();
+    for word in &grammar.words {
+        let entered = left_words.entry(&word.identifier).or_default();
         for rule in &word.rules {
             for reg in &rule.source.regs {
-                if let ast::RuleSourceItem::Identifier(id) = reg {
-                    let rule_index = words.get(&id.internal).unwrap().0;
-                    if rule_index <= index {
-                        return Err(format!(
-                            "Rule {} is not defined before rule {}",
-                            id.internal, index
-                        ));
+                let item = match reg {
+                    ast::RuleSourceItem::Repetition(repetition) => repetition.item.as_ref(),
+                    reg => reg,
+                };
+                if let ast::RuleSourceItem::Identifier(id) = item {
+                    entered.push(&id.internal);
+                }
+                if !nullable(reg, &nullable_words) {
+                    break;
+                }
+            }
+        }
+    }
+    fn visit<'a>(
+        word: &'a str,
+        left_words: &HashMap<&'a str, Vec<&'a str>>,
+        path: &mut Vec<&'a str>,
+        finished: &mut HashSet<&'a str>,
+    ) -> ElaborationResult<()> {
+        if let Some(start) = path.iter().position(|w| *w == word) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(word);
+            return Err(ElaborationError::new(
+                ErrorLocation::Definition(word.to_string()),
+                format!(
+                    "Word {} is left recursive, as it can be used again without consuming input: {}",
+                    word,
+                    cycle.join(" -> ")
+                ),
+            ));
+        }
+        if finished.contains(word) {
+            return Ok(());
+        }
+        path.push(word);
+        for entered in &left_words[word] {
+            visit(entered, left_words, path, finished)?;
+        }
+        path.pop();
+        finished.insert(word);
+        Ok(())
+    }
+    let mut finished = HashSet::new();
+    for word in &grammar.words {
+        visit(&word.identifier, &left_words, &mut Vec::new(), &mut finished)?;
+    }
+    Ok(grammar)
+}
+
+fn nullable(reg: &ast::RuleSourceItem, nullable_words: &HashSet<String>) -> bool {
+    match reg {
+        ast::RuleSourceItem::Identifier(id) => nullable_words.contains(&id.internal),
+        ast::RuleSourceItem::Repetition(repetition) => {
+            repetition.kind != ast::RepetitionKind::OneOrMore
+                || nullable(&repetition.item, nullable_words)
+        }
+        ast::RuleSourceItem::RegexString(s) => matches_empty(s),
+        ast::RuleSourceItem::Nested(_) => false,
+    }
+}
+
+/// Gives the words that can match the empty word.
+fn nullable_words(grammar: &ast::Grammar) -> HashSet<String> {
+    let mut nullable_words = HashSet::new();
+    loop {
+        let new_words: Vec<String> = grammar
+            .words
+            .iter()
+            .filter(|word| !nullable_words.contains(&word.identifier))
+            .filter(|word| {
+                word.rules.iter().any(|rule| {
+                    rule.source
+                        .regs
+                        .iter()
+                        .all(|reg| nullable(reg, &nullable_words))
+                })
+            })
+            .map(|word| word.identifier.clone())
+            .collect();
+        if new_words.is_empty() {
+            return nullable_words;
+        }
+        nullable_words.extend(new_words);
+    }
+}
+
+/// Checks that no repeated identifier can match the empty word, as it could then be repeated forever.
+fn check_repetitions(grammar: ast::Grammar) -> ElaborationResult<ast::Grammar> {
+    let nullable_words = nullable_words(&grammar);
+    for word in &grammar.words {
+        for rule in &word.rules {
+            for reg in &rule.source.regs {
+                if let ast::RuleSourceItem::Repetition(repetition) = reg {
+                    if let ast::RuleSourceItem::Identifier(id) = repetition.item.as_ref() {
+                        if nullable_words.contains(&id.internal) {
+                            return Err(ElaborationError::new(
+                                ErrorLocation::ReferenceIn(id.internal.clone(), word.identifier.clone()),
+                                format!(
+                                    "Word {} is repeated in word {}, but can match the empty word",
+                                    id.internal, word.identifier
+                                ),
+                            ));
+                        }
                     }
                 }
             }
@@ -131,3 +641,163 @@ fn check_rule_order(grammar: ast::Grammar) -> ElaborationResult<ast::Grammar> {
     }
     Ok(grammar)
 }
+
+/// Gives the word that is used by an item of a rule, also inside a nested block or repetition.
+fn used_word(reg: &ast::RuleSourceItem) -> Option<&str> {
+    match reg {
+        ast::RuleSourceItem::Identifier(id) => Some(&id.internal),
+        ast::RuleSourceItem::Nested(nested) => Some(&nested.rule.internal),
+        ast::RuleSourceItem::Repetition(repetition) => used_word(&repetition.item),
+        ast::RuleSourceItem::RegexString(_) => None,
+    }
+}
+
+fn productive(reg: &ast::RuleSourceItem, productive_words: &HashSet<&str>) -> bool {
+    match reg {
+        ast::RuleSourceItem::Identifier(id) => productive_words.contains(id.internal.as_str()),
+        ast::RuleSourceItem::Nested(nested) => productive_words.contains(nested.rule.internal.as_str()),
+        ast::RuleSourceItem::Repetition(repetition) => {
+            repetition.kind != ast::RepetitionKind::OneOrMore
+                || productive(&repetition.item, productive_words)
+        }
+        ast::RuleSourceItem::RegexString(_) => true,
+    }
+}
+
+/// Gives the words that can match a finite input, which have a rule that only uses such words.
+fn productive_words(grammar: &ast::Grammar) -> HashSet<&str> {
+    let mut productive_words = HashSet::new();
+    loop {
+        let new_words: Vec<&str> = grammar
+            .words
+            .iter()
+            .filter(|word| !productive_words.contains(word.identifier.as_str()))
+            .filter(|word| {
+                word.rules.iter().any(|rule| {
+                    rule.source
+                        .regs
+                        .iter()
+                        .all(|reg| productive(reg, &productive_words))
+                })
+            })
+            .map(|word| word.identifier.as_str())
+            .collect();
+        if new_words.is_empty() {
+            return productive_words;
+        }
+        productive_words.extend(new_words);
+    }
+}
+
+/// Gives the words that can be reached from the start word.
+fn reachable_words(grammar: &ast::Grammar) -> HashSet<&str> {
+    let words = convert_to_hashmap(grammar);
+    let mut reachable = HashSet::new();
+    let mut stack: Vec<&str> = grammar.words.first().map(|word| word.identifier.as_str()).into_iter().collect();
+    while let Some(word) = stack.pop() {
+        if !reachable.insert(word) {
+            continue;
+        }
+        let Some((_, word)) = words.get(word) else {
+            continue;
+        };
+        for rule in &word.rules {
+            stack.extend(rule.source.regs.iter().filter_map(used_word));
+        }
+    }
+    reachable
+}
+
+/// Gives the warnings about the words of a grammar, in the order of the words.
+pub(crate) fn warnings(grammar: &ast::Grammar) -> Vec<Warning> {
+    let reachable = reachable_words(grammar);
+    let productive = productive_words(grammar);
+    let used: HashSet<&str> = grammar
+        .words
+        .iter()
+        .flat_map(|word| {
+            word.rules
+                .iter()
+                .flat_map(|rule| rule.source.regs.iter().filter_map(used_word))
+                .filter(|used| *used != word.identifier)
+        })
+        .collect();
+    let mut warnings = Vec::new();
+    for word in &grammar.words {
+        let warning = |kind| Warning {
+            kind,
+            word: word.identifier.clone(),
+        };
+        let name = word.identifier.as_str();
+        if !reachable.contains(name) {
+            if used.contains(name) {
+                warnings.push(warning(WarningKind::Unreachable));
+            } else {
+                warnings.push(warning(WarningKind::Unused));
+            }
+        }
+        if !productive.contains(name) {
+            warnings.push(warning(WarningKind::Unproductive));
+        }
+    }
+    warnings
+}
+
+/// Gives the symbols of a grammar, once for every kind and regex, in the order they are used.
+fn symbols(grammar: &ast::Grammar) -> Vec<Symbol> {
+    fn add(reg: &ast::RuleSourceItem, word: &str, symbols: &mut Vec<Symbol>) {
+        let mut push = |kind, regex: &str| {
+            if !symbols.iter().any(|s| s.kind == kind && s.regex == regex) {
+                symbols.push(Symbol {
+                    kind,
+                    regex: regex.to_string(),
+                    word: word.to_string(),
+                });
+            }
+        };
+        match reg {
+            ast::RuleSourceItem::RegexString(regex) => push(SymbolKind::Internal, regex),
+            ast::RuleSourceItem::Nested(nested) => {
+                push(SymbolKind::Call, &nested.call_symbol);
+                push(SymbolKind::Return, &nested.return_symbol);
+            }
+            ast::RuleSourceItem::Repetition(repetition) => add(&repetition.item, word, symbols),
+            ast::RuleSourceItem::Identifier(_) => {}
+        }
+    }
+    let mut symbols = Vec::new();
+    for word in &grammar.words {
+        for rule in &word.rules {
+            for reg in &rule.source.regs {
+                add(reg, &word.identifier, &mut symbols);
+            }
+        }
+    }
+    symbols
+}
+
+/// Gives the pairs of symbols of different kinds that match the same input.
+pub(crate) fn symbol_overlaps(grammar: &ast::Grammar) -> Vec<SymbolOverlap> {
+    let mut symbols = symbols(grammar);
+    symbols.sort_by_key(|symbol| match symbol.kind {
+        SymbolKind::Call => 0,
+        SymbolKind::Return => 1,
+        SymbolKind::Internal => 2,
+    });
+    let mut overlaps = Vec::new();
+    for (i, first) in symbols.iter().enumerate() {
+        for second in &symbols[i + 1..] {
+            if first.kind == second.kind {
+                continue;
+            }
+            if let Some(witness) = intersection(&first.regex, &second.regex) {
+                overlaps.push(SymbolOverlap {
+                    first: first.clone(),
+                    second: second.clone(),
+                    witness,
+                });
+            }
+        }
+    }
+    overlaps
+}
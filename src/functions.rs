@@ -0,0 +1,187 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+/// A function that can be applied to values in the transform of a rule.
+///
+/// The function receives the translated arguments in order and returns the replacement text.
+pub type Function = Box<dyn Fn(&[String]) -> String + Send + Sync>;
+
+/// A function as it is registered, which gives `None` if it can not be applied to the arguments.
+type Partial = Box<dyn Fn(&[String]) -> Option<String> + Send + Sync>;
+
+/// Registry of the functions that can be used in the transform of a rule, like `html(value)`.
+///
+/// The default registry contains the built-in functions:
+/// * `upper(x)` and `lower(x)` change the case of `x`.
+/// * `trim(x)` removes leading and trailing whitespace of `x`.
+/// * `html(x)` escapes `x` for use in HTML text and attribute values.
+/// * `json(x)` escapes `x` for use inside a JSON string.
+/// * `lpad(x, "n")` and `rpad(x, "n")` pad `x` with spaces on the left or right to a width of `n` characters. A
+///   width that is not a number, or is larger than [`MAX_WIDTH`], is refused when the translator is created, or
+///   fails the translation if the width is translated from the input.
+///
+/// # Examples
+///
+/// ```
+/// use vpl_parser_generator::{FunctionRegistry, Translator};
+///
+/// let mut functions = FunctionRegistry::default();
+/// functions.register("reverse", 1, |args| args[0].chars().rev().collect());
+/// let translator = Translator::with_functions("A:\n  \"(?P<v>[a-z]+)\" -> reverse(upper(v))", functions).unwrap();
+/// assert_eq!(translator.translate("abc"), Some("CBA".to_string()));
+///
+/// let translator = Translator::new("A:\n  \"(?P<v>[a-z]+) (?P<w>[0-9a-z]+)\" -> \"[\" lpad(v, w) \"]\"").unwrap();
+/// assert_eq!(translator.translate("ab 4"), Some("[  ab]".to_string()));
+/// assert_eq!(translator.translate("ab x"), None);
+/// assert_eq!(translator.translate("ab 99999999999"), None);
+/// assert!(Translator::new("A:\n  \"(?P<v>[a-z]+)\" -> rpad(v, \"x\")").is_err());
+///
+/// let translator = Translator::new("A:\n  \"(?P<v>[a-z<]+)\" -> html(v) \" \" upper(v)").unwrap();
+/// assert_eq!(translator.translate("a<"), Some("a&lt; A<".to_string()));
+/// assert!(Translator::new("A:\n  \"(?P<v>[a-z]+)\" -> v v").is_err());
+/// ```
+pub struct FunctionRegistry {
+    functions: HashMap<String, (usize, Partial)>,
+    /// The functions whose last argument is a width, which is checked when it is a string in the transform.
+    widths: HashSet<String>,
+}
+
+impl FunctionRegistry {
+    /// Creates a registry without any functions, not even the built-in ones.
+    pub fn new() -> Self {
+        Self {
+            functions: HashMap::new(),
+            widths: HashSet::new(),
+        }
+    }
+
+    /// Registers a function with the given name and number of arguments, replacing a function with the same name.
+    pub fn register<F>(&mut self, name: &str, arity: usize, function: F)
+    where
+        F: Fn(&[String]) -> String + Send + Sync + 'static,
+    {
+        self.widths.remove(name);
+        self.functions
+            .insert(name.to_string(), (arity, Box::new(move |args| Some(function(args)))));
+    }
+
+    /// Registers a built-in function whose last argument is a width.
+    fn register_padding(&mut self, name: &str, left: bool) {
+        self.widths.insert(name.to_string());
+        self.functions
+            .insert(name.to_string(), (2, Box::new(move |args| pad(&args[0], &args[1], left))));
+    }
+
+    /// Gives the number of arguments of a function, if it is registered.
+    pub fn arity(&self, name: &str) -> Option<usize> {
+        self.functions.get(name).map(|(arity, _)| *arity)
+    }
+
+    /// Gives the names of the registered functions, sorted.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.functions.keys().map(String::as_str).collect();
+        names.sort();
+        names
+    }
+
+    /// Checks if the last argument of a function is a width.
+    pub(crate) fn takes_width(&self, name: &str) -> bool {
+        self.widths.contains(name)
+    }
+
+    pub(crate) fn apply(&self, name: &str, arguments: &[String]) -> Option<String> {
+        self.functions
+            .get(name)
+            .and_then(|(_, function)| function(arguments))
+    }
+}
+
+impl Default for FunctionRegistry {
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register("upper", 1, |args| args[0].to_uppercase());
+        registry.register("lower", 1, |args| args[0].to_lowercase());
+        registry.register("trim", 1, |args| args[0].trim().to_string());
+        registry.register("html", 1, |args| escape_html(&args[0]));
+        registry.register("json", 1, |args| escape_json(&args[0]));
+        registry.register_padding("lpad", true);
+        registry.register_padding("rpad", false);
+        registry
+    }
+}
+
+impl fmt::Debug for FunctionRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.functions.iter().map(|(name, (arity, _))| (name, arity)))
+            .finish()
+    }
+}
+
+fn escape_html(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#39;"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+fn escape_json(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// The largest width of `lpad` and `rpad`, so a width that is translated from the input can not make the translation
+/// allocate an arbitrary amount of memory.
+pub const MAX_WIDTH: usize = 65536;
+
+/// Parses the width argument of a padding function, which is at most [`MAX_WIDTH`].
+pub(crate) fn parse_width(width: &str) -> Option<usize> {
+    width.trim().parse().ok().filter(|width| *width <= MAX_WIDTH)
+}
+
+/// Pads the value with spaces up to the width, or gives `None` if the width is not a number up to [`MAX_WIDTH`].
+fn pad(value: &str, width: &str, left: bool) -> Option<String> {
+    let width = parse_width(width)?;
+    let padding = " ".repeat(width.saturating_sub(value.chars().count()));
+    Some(if left {
+        padding + value
+    } else {
+        value.to_string() + &padding
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pad, MAX_WIDTH};
+    use crate::Translator;
+
+    #[test]
+    fn widths_are_limited() {
+        assert_eq!(pad("ab", "4", true), Some("  ab".to_string()));
+        assert_eq!(pad("ab", &MAX_WIDTH.to_string(), false).map(|s| s.len()), Some(MAX_WIDTH));
+        assert_eq!(pad("ab", &(MAX_WIDTH + 1).to_string(), false), None);
+        assert_eq!(pad("ab", "99999999999", true), None);
+        assert!(Translator::new("A:\n  \"(?P<v>[a-z]+)\" -> lpad(v, \"99999999999\")").is_err());
+    }
+}
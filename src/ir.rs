@@ -1,10 +1,12 @@
 use std::collections::HashMap;
 
-use regex::Regex;
-
 use crate::ast::{self};
+use crate::regex::capture_names;
 
 type Id = usize;
+
+/// Name of the function that joins the translations of a repeated identifier with a separator.
+pub const JOIN: &str = "join";
 type IRFrom<'a, T> = (&'a ast::Grammar, &'a T);
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -25,6 +27,7 @@ impl From<&ast::Grammar> for Grammar {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Nonterminal {
     pub identifier: Id,
+    pub name: String,
     pub rules: Vec<Rule>,
 }
 
@@ -41,6 +44,7 @@ impl From<IRFrom<'_, ast::Nonterminal>> for Nonterminal {
                 .position(|w| w.identifier == word.identifier)
                 .unwrap()
                 + 1,
+            name: word.identifier.clone(),
             rules,
         }
     }
@@ -83,11 +87,14 @@ impl From<(&ast::Grammar, &ast::RuleTransform, &TransformMap)> for RuleTransform
 pub enum RuleTransformItem {
     String(String),
     Identifier(Id),
+    Function(Function),
+    /// A repeated identifier, with the separator between its translations.
+    Join(Id, String),
 }
 
 impl From<(&ast::Grammar, &ast::RuleTransformItem, &TransformMap)> for RuleTransformItem {
     fn from(
-        (_grammar, rule_transform_item, external_map): (
+        (grammar, rule_transform_item, external_map): (
             &ast::Grammar,
             &ast::RuleTransformItem,
             &TransformMap,
@@ -98,6 +105,38 @@ impl From<(&ast::Grammar, &ast::RuleTransformItem, &TransformMap)> for RuleTrans
             ast::RuleTransformItem::Identifier(identifier) => {
                 Self::Identifier(*external_map.get(identifier).unwrap())
             }
+            ast::RuleTransformItem::Function(function) if function.name == JOIN => {
+                match &function.arguments[..] {
+                    [ast::RuleTransformItem::Identifier(identifier), ast::RuleTransformItem::String(separator)] => {
+                        Self::Join(*external_map.get(identifier).unwrap(), separator.clone())
+                    }
+                    _ => unreachable!("The arguments of join are checked by the elaborator"),
+                }
+            }
+            ast::RuleTransformItem::Function(function) => {
+                Self::Function(Function::from((grammar, function, external_map)))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    pub name: String,
+    pub arguments: Vec<RuleTransformItem>,
+}
+
+impl From<(&ast::Grammar, &ast::Function, &TransformMap)> for Function {
+    fn from(
+        (grammar, function, external_map): (&ast::Grammar, &ast::Function, &TransformMap),
+    ) -> Self {
+        Self {
+            name: function.name.clone(),
+            arguments: function
+                .arguments
+                .iter()
+                .map(|argument| RuleTransformItem::from((grammar, argument, external_map)))
+                .collect(),
         }
     }
 }
@@ -124,6 +163,7 @@ pub enum RuleSourceItem {
     RegexString(String),
     Identifier(Identifier),
     Nested(Nested),
+    Repetition(Repetition),
 }
 
 impl From<(&ast::Grammar, &ast::RuleSourceItem, &TransformMap)> for RuleSourceItem {
@@ -140,6 +180,32 @@ impl From<(&ast::Grammar, &ast::RuleSourceItem, &TransformMap)> for RuleSourceIt
             ast::RuleSourceItem::Nested(nested) => {
                 Self::Nested(Nested::from((grammar, nested, external_map)))
             }
+            ast::RuleSourceItem::Repetition(repetition) => {
+                Self::Repetition(Repetition::from((grammar, repetition, external_map)))
+            }
+        }
+    }
+}
+
+pub use ast::RepetitionKind;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repetition {
+    pub item: Box<RuleSourceItem>,
+    pub kind: RepetitionKind,
+}
+
+impl From<(&ast::Grammar, &ast::Repetition, &TransformMap)> for Repetition {
+    fn from(
+        (grammar, repetition, external_map): (&ast::Grammar, &ast::Repetition, &TransformMap),
+    ) -> Self {
+        Self {
+            item: Box::new(RuleSourceItem::from((
+                grammar,
+                repetition.item.as_ref(),
+                external_map,
+            ))),
+            kind: repetition.kind,
         }
     }
 }
@@ -185,40 +251,34 @@ impl From<(&ast::Grammar, &ast::Identifier, &TransformMap)> for Identifier {
 
 type TransformMap = HashMap<String, usize>;
 
+/// Maps the identifiers and capture groups of the source of a rule to their position in the parse tree node.
 fn transform_map(rule: &ast::Rule) -> TransformMap {
-    let mut map = HashMap::new();
-    let mut i = 0;
-    rule.source.regs.iter().for_each(|reg| match reg {
-        ast::RuleSourceItem::Identifier(identifier) => {
-            map.insert(identifier.external.clone(), i);
-            i += 1;
-        }
-        ast::RuleSourceItem::RegexString(s) => {
-            let regex = Regex::new(s).unwrap();
-            regex.capture_names().for_each(|name| {
-                if let Some(name) = name {
-                    map.insert(name.to_string(), i);
-                    i += 1;
-                }
-            });
+    fn insert_regex(regex: &str, map: &mut TransformMap) {
+        for name in capture_names(regex) {
+            let i = map.len();
+            map.insert(name, i);
         }
-        ast::RuleSourceItem::Nested(nested) => {
-            let regex = Regex::new(&nested.call_symbol).unwrap();
-            regex.capture_names().for_each(|name| {
-                if let Some(name) = name {
-                    map.insert(name.to_string(), i);
-                    i += 1;
-                }
-            });
-            map.insert(nested.rule.external.clone(), i);
-            let regex = Regex::new(&nested.return_symbol).unwrap();
-            regex.capture_names().for_each(|name| {
-                if let Some(name) = name {
-                    map.insert(name.to_string(), i);
-                    i += 1;
-                }
-            });
+    }
+    fn insert_item(reg: &ast::RuleSourceItem, map: &mut TransformMap) {
+        match reg {
+            ast::RuleSourceItem::Identifier(identifier) => {
+                let i = map.len();
+                map.insert(identifier.external.clone(), i);
+            }
+            ast::RuleSourceItem::RegexString(s) => insert_regex(s, map),
+            ast::RuleSourceItem::Nested(nested) => {
+                insert_regex(&nested.call_symbol, map);
+                let i = map.len();
+                map.insert(nested.rule.external.clone(), i);
+                insert_regex(&nested.return_symbol, map);
+            }
+            ast::RuleSourceItem::Repetition(repetition) => insert_item(&repetition.item, map),
         }
-    });
+    }
+    let mut map = HashMap::new();
+    rule.source
+        .regs
+        .iter()
+        .for_each(|reg| insert_item(reg, &mut map));
     map
 }
@@ -1,10 +1,22 @@
 mod ast;
+pub mod codegen;
 mod elaborator;
+mod functions;
 mod ir;
 mod parser;
+pub mod railroad;
 mod regex;
+mod sampler;
+mod serialize;
 mod translator;
 mod recognizer;
 
-pub use recognizer::Recognizer;
+pub use elaborator::{
+    check, check_all, check_alphabet, ElaborationError, ErrorLocation, Symbol, SymbolKind, SymbolOverlap, Warning,
+    WarningKind,
+};
+pub use functions::{Function, FunctionRegistry, MAX_WIDTH};
+pub use recognizer::{Child, Coverage, Event, Events, Node, Recognizer, RecognizerStream, Tree};
+pub use sampler::Sampler;
+pub use serialize::FORMAT_VERSION;
 pub use translator::Translator;
\ No newline at end of file
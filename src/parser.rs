@@ -7,9 +7,10 @@ use nom::{
 };
 
 use crate::ast::{
-    Grammar, Identifier, Nested, Nonterminal, Rule, RuleSource, RuleSourceItem, RuleTransform,
-    RuleTransformItem,
+    Function, Grammar, Identifier, Nested, Nonterminal, Repetition, RepetitionKind, Rule,
+    RuleSource, RuleSourceItem, RuleTransform, RuleTransformItem,
 };
+use crate::elaborator::{ElaborationError, ErrorLocation};
 
 fn alphanumeric(inp: &str) -> IResult<&str, &str> {
     let (inp, string) = nom::bytes::complete::take_while1(char::is_alphanumeric)(inp)?;
@@ -40,6 +41,23 @@ impl Grammar {
         let (inp, words) = nom::multi::many1(Nonterminal::parse)(inp)?;
         Ok((inp, Self { words }))
     }
+
+    /// Parses an entire grammar file, or gives the line and column at which parsing stopped.
+    pub fn parse_complete(inp: &str) -> Result<Self, ElaborationError> {
+        let rest = match Self::parse(inp) {
+            Ok(("", grammar)) => return Ok(grammar),
+            Ok((rest, _)) => rest,
+            Err(nom::Err::Error(e) | nom::Err::Failure(e)) => e.input,
+            Err(nom::Err::Incomplete(_)) => "",
+        };
+        let parsed = &inp[..inp.len() - rest.len()];
+        let line = parsed.matches('\n').count() + 1;
+        let column = parsed.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+        Err(ElaborationError::new(
+            ErrorLocation::Position(line, column),
+            format!("Error, entire file not parsed, stopped at line {line}, column {column}"),
+        ))
+    }
 }
 
 impl Nonterminal {
@@ -82,6 +100,8 @@ impl RuleTransformItem {
         if inp.starts_with('\"') {
             let (inp, regex) = parse_regex(inp)?;
             Ok((inp, Self::String(regex.to_string())))
+        } else if let Ok((inp, function)) = Function::parse(inp) {
+            Ok((inp, Self::Function(function)))
         } else {
             let (inp, string) = alphanumeric(inp)?;
             Ok((inp, Self::Identifier(string.to_string())))
@@ -89,6 +109,25 @@ impl RuleTransformItem {
     }
 }
 
+impl Function {
+    pub fn parse(inp: &str) -> IResult<&str, Self> {
+        let (inp, name) = alphanumeric(inp)?;
+        let (inp, _) = tag("(")(inp)?;
+        let (inp, arguments) = nom::multi::separated_list0(
+            delimited(opt(skip_space), tag(","), opt(skip_space)),
+            RuleTransformItem::parse,
+        )(inp)?;
+        let (inp, _) = tag(")")(inp)?;
+        Ok((
+            inp,
+            Self {
+                name: name.to_string(),
+                arguments,
+            },
+        ))
+    }
+}
+
 impl Nested {
     pub fn parse(inp: &str) -> IResult<&str, Self> {
         let (inp, _) = tag("[")(inp)?;
@@ -129,13 +168,33 @@ impl RuleSource {
 impl RuleSourceItem {
     pub fn parse(inp: &str) -> IResult<&str, Self> {
         if let Ok((inp, nested)) = Nested::parse(inp) {
-            return Ok((inp, Self::Nested(nested)));
+            return Repetition::parse(inp, Self::Nested(nested));
         }
         if let Ok((inp, regex_string)) = parse_regex(inp) {
             return Ok((inp, Self::RegexString(regex_string.to_string())));
         }
         let (inp, id) = Identifier::parse(inp)?;
-        Ok((inp, Self::Identifier(id)))
+        Repetition::parse(inp, Self::Identifier(id))
+    }
+}
+
+impl Repetition {
+    /// Parses an optional `*`, `+` or `?` directly after a nested block or identifier.
+    pub fn parse(inp: &str, item: RuleSourceItem) -> IResult<&str, RuleSourceItem> {
+        let (inp, kind) = opt(one_of("*+?"))(inp)?;
+        let kind = match kind {
+            Some('*') => RepetitionKind::ZeroOrMore,
+            Some('+') => RepetitionKind::OneOrMore,
+            Some('?') => RepetitionKind::Optional,
+            _ => return Ok((inp, item)),
+        };
+        Ok((
+            inp,
+            RuleSourceItem::Repetition(Self {
+                item: Box::new(item),
+                kind,
+            }),
+        ))
     }
 }
 
@@ -0,0 +1,302 @@
+//! Railroad diagrams of the nonterminals of a grammar, as SVG images and an HTML page that indexes them.
+//!
+//! Every rule of a nonterminal is a branch of the diagram. Regular expressions are drawn as rounded boxes, other
+//! nonterminals as square boxes that link to their diagram, and nested call/return blocks as a dashed box around
+//! the call symbol, the nonterminal and the return symbol.
+
+use std::{error::Error, fmt::Write};
+
+use crate::ast;
+
+/// Width of a character of the monospace font of the boxes.
+const CHAR_WIDTH: usize = 8;
+/// Distance from the line to the top or bottom of a box.
+const BOX_HALF_HEIGHT: usize = 11;
+/// Horizontal space between items, and vertical space between branches.
+const GAP: usize = 10;
+/// Horizontal space used by the lines that split into and join the branches.
+const BRANCH: usize = 20;
+
+const STYLE: &str = "\
+path { fill: none; stroke: #333; stroke-width: 1.5; }
+rect { fill: #f5f5ff; stroke: #333; stroke-width: 1.5; }
+rect.call, rect.return { fill: #fff2dd; }
+rect.nested { fill: none; stroke-dasharray: 4 3; stroke: #999; }
+text { font-family: monospace; font-size: 13px; text-anchor: middle; dominant-baseline: central; }
+a text { fill: #0645ad; text-decoration: underline; }";
+
+enum Diagram {
+    /// A regular expression, with the class of its box.
+    Terminal(String, &'static str),
+    NonTerminal(String),
+    Sequence(Vec<Diagram>),
+    /// Branches that are stacked under each other, the first one is on the line.
+    Choice(Vec<Diagram>),
+    OneOrMore(Box<Diagram>),
+    Nested(Box<Diagram>),
+    Skip,
+}
+
+impl Diagram {
+    fn from_rules(rules: &[ast::Rule]) -> Self {
+        Diagram::Choice(
+            rules
+                .iter()
+                .map(|rule| Diagram::Sequence(rule.source.regs.iter().map(Diagram::from_item).collect()))
+                .collect(),
+        )
+    }
+
+    fn from_item(item: &ast::RuleSourceItem) -> Self {
+        match item {
+            ast::RuleSourceItem::RegexString(regex) => Diagram::Terminal(regex.clone(), "terminal"),
+            ast::RuleSourceItem::Identifier(identifier) => Diagram::NonTerminal(identifier.internal.clone()),
+            ast::RuleSourceItem::Nested(nested) => Diagram::Nested(Box::new(Diagram::Sequence(vec![
+                Diagram::Terminal(nested.call_symbol.clone(), "call"),
+                Diagram::NonTerminal(nested.rule.internal.clone()),
+                Diagram::Terminal(nested.return_symbol.clone(), "return"),
+            ]))),
+            ast::RuleSourceItem::Repetition(repetition) => {
+                let item = Diagram::from_item(&repetition.item);
+                match repetition.kind {
+                    ast::RepetitionKind::Optional => Diagram::Choice(vec![item, Diagram::Skip]),
+                    ast::RepetitionKind::OneOrMore => Diagram::OneOrMore(Box::new(item)),
+                    ast::RepetitionKind::ZeroOrMore => Diagram::Choice(vec![
+                        Diagram::OneOrMore(Box::new(item)),
+                        Diagram::Skip,
+                    ]),
+                }
+            }
+        }
+    }
+
+    /// Gives the width, and the height above and below the line.
+    fn size(&self) -> (usize, usize, usize) {
+        match self {
+            Diagram::Terminal(text, _) | Diagram::NonTerminal(text) => (
+                text.chars().count() * CHAR_WIDTH + 2 * GAP,
+                BOX_HALF_HEIGHT,
+                BOX_HALF_HEIGHT,
+            ),
+            Diagram::Sequence(items) => {
+                let sizes: Vec<_> = items.iter().map(Diagram::size).collect();
+                let width = sizes.iter().map(|s| s.0).sum::<usize>() + GAP * sizes.len().saturating_sub(1);
+                let up = sizes.iter().map(|s| s.1).max().unwrap_or(0);
+                let down = sizes.iter().map(|s| s.2).max().unwrap_or(0);
+                (width, up, down)
+            }
+            Diagram::Choice(branches) => {
+                let sizes: Vec<_> = branches.iter().map(Diagram::size).collect();
+                let width = sizes.iter().map(|s| s.0).max().unwrap_or(0) + 2 * BRANCH;
+                let down = sizes[1..]
+                    .iter()
+                    .fold(sizes[0].2, |down, size| down + GAP + size.1 + size.2);
+                (width, sizes[0].1, down)
+            }
+            Diagram::OneOrMore(item) => {
+                let (width, up, down) = item.size();
+                (width + 2 * GAP, up, down + GAP)
+            }
+            Diagram::Nested(item) => {
+                let (width, up, down) = item.size();
+                (width + 2 * GAP, up + GAP, down + GAP)
+            }
+            Diagram::Skip => (0, 0, 0),
+        }
+    }
+
+    /// Draws the diagram with the line starting at `(x, y)`, linking nonterminals with `link`.
+    fn draw(&self, x: usize, y: usize, link: &dyn Fn(&str) -> String, svg: &mut String) -> std::fmt::Result {
+        match self {
+            Diagram::Terminal(text, class) => {
+                let (width, _, _) = self.size();
+                writeln!(
+                    svg,
+                    "<rect class=\"{class}\" x=\"{x}\" y=\"{}\" width=\"{width}\" height=\"{}\" rx=\"{BOX_HALF_HEIGHT}\"/>",
+                    y - BOX_HALF_HEIGHT,
+                    2 * BOX_HALF_HEIGHT
+                )?;
+                writeln!(svg, "<text x=\"{}\" y=\"{y}\">{}</text>", x + width / 2, escape(text))
+            }
+            Diagram::NonTerminal(name) => {
+                let (width, _, _) = self.size();
+                writeln!(svg, "<a href=\"{}\">", escape(&link(name)))?;
+                writeln!(
+                    svg,
+                    "<rect x=\"{x}\" y=\"{}\" width=\"{width}\" height=\"{}\"/>",
+                    y - BOX_HALF_HEIGHT,
+                    2 * BOX_HALF_HEIGHT
+                )?;
+                writeln!(svg, "<text x=\"{}\" y=\"{y}\">{}</text>", x + width / 2, escape(name))?;
+                writeln!(svg, "</a>")
+            }
+            Diagram::Sequence(items) => {
+                let mut x = x;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(svg, "<path d=\"M{x} {y}h{GAP}\"/>")?;
+                        x += GAP;
+                    }
+                    item.draw(x, y, link, svg)?;
+                    x += item.size().0;
+                }
+                Ok(())
+            }
+            Diagram::Choice(branches) => {
+                let (width, _, _) = self.size();
+                let mut branch_y = y;
+                let mut previous_down = 0;
+                for (i, branch) in branches.iter().enumerate() {
+                    let (branch_width, up, down) = branch.size();
+                    if i > 0 {
+                        branch_y += previous_down + GAP + up;
+                    }
+                    previous_down = down;
+                    let fill = width - 2 * BRANCH - branch_width;
+                    let half = BRANCH / 2;
+                    let drop = branch_y - y;
+                    writeln!(
+                        svg,
+                        "<path d=\"M{x} {y}h{half}v{drop}h{half}M{} {branch_y}h{}v-{drop}h{half}\"/>",
+                        x + BRANCH + branch_width,
+                        fill + half
+                    )?;
+                    branch.draw(x + BRANCH, branch_y, link, svg)?;
+                }
+                Ok(())
+            }
+            Diagram::OneOrMore(item) => {
+                let (width, _, down) = self.size();
+                let loop_y = y + down;
+                writeln!(
+                    svg,
+                    "<path d=\"M{x} {y}h{GAP}M{} {y}h{GAP}M{} {y}v{}h-{}v-{}\"/>",
+                    x + width - GAP,
+                    x + width - GAP / 2,
+                    loop_y - y,
+                    width - GAP,
+                    loop_y - y
+                )?;
+                item.draw(x + GAP, y, link, svg)
+            }
+            Diagram::Nested(item) => {
+                let (width, up, down) = self.size();
+                writeln!(
+                    svg,
+                    "<rect class=\"nested\" x=\"{x}\" y=\"{}\" width=\"{width}\" height=\"{}\" rx=\"4\"/>",
+                    y - up,
+                    up + down
+                )?;
+                writeln!(svg, "<path d=\"M{x} {y}h{GAP}M{} {y}h{GAP}\"/>", x + width - GAP)?;
+                item.draw(x + GAP, y, link, svg)
+            }
+            Diagram::Skip => Ok(()),
+        }
+    }
+
+    /// Draws the diagram as a complete SVG image, with a start and end mark on the line.
+    fn svg(&self, link: &dyn Fn(&str) -> String) -> Result<String, std::fmt::Error> {
+        let (width, up, down) = self.size();
+        let margin = 2 * GAP;
+        let (total_width, height) = (width + 2 * margin, up + down + 2 * GAP);
+        let y = up + GAP;
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{total_width}\" height=\"{height}\" viewBox=\"0 0 {total_width} {height}\">"
+        )?;
+        writeln!(svg, "<style>\n{STYLE}\n</style>")?;
+        writeln!(
+            svg,
+            "<path d=\"M{GAP} {}v{}M{GAP} {y}h{GAP}M{} {y}h{GAP}M{} {}v{}\"/>",
+            y - GAP,
+            2 * GAP,
+            margin + width,
+            margin + width + GAP,
+            y - GAP,
+            2 * GAP
+        )?;
+        self.draw(margin, y, link, &mut svg)?;
+        writeln!(svg, "</svg>")?;
+        Ok(svg)
+    }
+}
+
+/// Renders a railroad diagram for every nonterminal of a grammar, as the name of the nonterminal and an SVG image.
+///
+/// Nonterminals link to the image of their diagram, `{name}.svg`, so the images can be saved next to each other.
+///
+/// # Examples
+///
+/// ```
+/// use vpl_parser_generator::railroad;
+///
+/// let diagrams = railroad::diagrams("A:\n  \"a\" B=B -> B\nB:\n  \"b\" -> \"b\"").unwrap();
+/// assert_eq!(diagrams[0].0, "A");
+/// assert!(diagrams[0].1.contains("<a href=\"B.svg\">"));
+/// ```
+pub fn diagrams(grammar: &str) -> Result<Vec<(String, String)>, Box<dyn Error + '_>> {
+    let grammar = checked_grammar(grammar)?;
+    let link = |name: &str| format!("{name}.svg");
+    let mut diagrams = Vec::new();
+    for word in &grammar.words {
+        let svg = Diagram::from_rules(&word.rules).svg(&link)?;
+        diagrams.push((word.identifier.clone(), svg));
+    }
+    Ok(diagrams)
+}
+
+/// Renders an HTML page with the railroad diagrams of all nonterminals of a grammar, which link to each other.
+///
+/// # Examples
+///
+/// ```
+/// use vpl_parser_generator::railroad;
+///
+/// let html = railroad::html("List:\n  [\"\\(\" Item=I \"\\)\"]* -> I\nItem:\n  \"[a-z]+\" -> \"i\"").unwrap();
+/// assert!(html.contains("<h2 id=\"Item\">Item</h2>"));
+/// assert!(html.contains("<a href=\"#Item\">"));
+/// assert!(railroad::html("A:\n  B=B -> B").is_err());
+/// ```
+pub fn html(grammar: &str) -> Result<String, Box<dyn Error + '_>> {
+    let grammar = checked_grammar(grammar)?;
+    let link = |name: &str| format!("#{name}");
+    let mut html = String::new();
+    writeln!(html, "<!DOCTYPE html>")?;
+    writeln!(html, "<html>")?;
+    writeln!(html, "<head>")?;
+    writeln!(html, "<meta charset=\"utf-8\">")?;
+    writeln!(html, "<title>Grammar</title>")?;
+    writeln!(html, "<style>body {{ font-family: sans-serif; }} h2 {{ font-family: monospace; }}</style>")?;
+    writeln!(html, "</head>")?;
+    writeln!(html, "<body>")?;
+    writeln!(html, "<h1>Grammar</h1>")?;
+    writeln!(html, "<ul>")?;
+    for word in &grammar.words {
+        let name = escape(&word.identifier);
+        writeln!(html, "<li><a href=\"#{name}\">{name}</a></li>")?;
+    }
+    writeln!(html, "</ul>")?;
+    for word in &grammar.words {
+        let name = escape(&word.identifier);
+        writeln!(html, "<h2 id=\"{name}\">{name}</h2>")?;
+        html.push_str(&Diagram::from_rules(&word.rules).svg(&link)?);
+    }
+    writeln!(html, "</body>")?;
+    writeln!(html, "</html>")?;
+    Ok(html)
+}
+
+/// Parses and checks the grammar, so only diagrams of grammars the recognizer accepts are rendered.
+fn checked_grammar(grammar: &str) -> Result<ast::Grammar, Box<dyn Error + '_>> {
+    let grammar = ast::Grammar::parse_complete(grammar)?;
+    crate::elaborator::elaborate(grammar.clone())?;
+    Ok(grammar)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
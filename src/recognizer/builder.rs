@@ -1,15 +1,19 @@
-use std::{collections::HashMap, cell::RefCell};
+use std::{collections::HashMap, sync::Arc};
 
-use crate::{ir::*, regex::RegexParser, Recognizer};
+use crate::{ir::*, regex::{capture_names, matches_empty, RegexParser}, Recognizer};
 
-use super::{recognizer_automaton::Action, State, NextMap};
+use super::{recognizer_automaton::Action, State, NextMap, Slot};
 
 pub(crate) struct RecognizerBuilder<'grammar> {
-    epsilon_rules: HashMap<(String, State), State>,
+    /// Return points of nested calls, grouped by their return symbol.
+    returns: HashMap<String, HashMap<State, (State, Slot)>>,
+    /// Return points of identifiers, which are left without consuming input.
+    exits: HashMap<State, State>,
     state: usize,
     state_to_rule: HashMap<State, (usize, usize)>,
     grammar: &'grammar Grammar,
     next_state: NextMap,
+    first: HashMap<usize, (Vec<String>, bool)>,
 }
 
 type Indexed<T> = (T, usize);
@@ -19,231 +23,283 @@ impl<'grammar> RecognizerBuilder<'grammar> {
     pub(crate) fn new(grammar: &'grammar Grammar) -> Self {
         Self {
             grammar,
-            epsilon_rules: HashMap::default(),
+            returns: HashMap::default(),
+            exits: HashMap::default(),
             state: usize::default(),
             state_to_rule: HashMap::default(),
             next_state: NextMap::default(),
+            first: HashMap::default(),
         }
     }
 
     pub(crate) fn build(mut self) -> Recognizer {
-        self.state += 1;
-        for (index, nonterminal) in self.grammar.nonterminals.iter().enumerate().rev() {
+        // States 1 up to the number of nonterminals are the starting states of the nonterminals.
+        self.state = self.grammar.nonterminals.len() + 1;
+        for (index, nonterminal) in self.grammar.nonterminals.iter().enumerate() {
             self.build_nonterminal(index, nonterminal);
         }
         self.build_epsilon_state();
         Recognizer {
-            stack: RefCell::new(Vec::new()),
-            state: 1,
-            next_state: self.next_state,
-            nonterminals_length: self.grammar.nonterminals.iter().len(),
+            next_state: Arc::new(self.next_state),
+            exits: Arc::new(self.exits),
+            names: Arc::new(self.grammar.nonterminals.iter().map(|n| n.name.clone()).collect()),
+            state_to_rule: Arc::new(self.state_to_rule),
+            coverage: None,
         }
     }
 
-    fn collect_starting_rules(&self, identifier: usize, rule_nr: usize) -> Vec<(String, Action)> {
-        // Get the index of the word in the grammar
-        let actions = &self.next_state[&(identifier + 1)];
-        actions
-            .1
-            .iter()
-            .enumerate()
-            .map(|(i, a)| {
-                (
-                    actions.0.original_strings.get(i).unwrap().clone(),
-                    add_id_to_action(a.clone(), identifier, rule_nr),
-                )
-            })
-            .collect()
-    }
-
     /// Builds the epsilon state, which has all nested returns.
     fn build_epsilon_state(&mut self) {
-        if !self.epsilon_rules.is_empty() {
-            let mapped_returns = self.epsilon_rules.iter().fold(
-                HashMap::new(),
-                |mut map, ((return_symbol, original_state), next_state)| {
-                    map.entry(return_symbol)
-                        .or_insert(HashMap::new())
-                        .insert(*original_state, *next_state);
-                    map
-                },
-            );
-            self.next_state.insert(
-                0,
-                (
-                    RegexParser::from(
-                        mapped_returns
-                            .keys()
-                            .map(|&s| s.clone())
-                            .collect::<Vec<String>>(),
-                    ),
-                    mapped_returns
-                        .values()
-                        .map(|map| Action::Return(map.clone()))
-                        .collect::<Vec<Action>>(),
-                ),
-            );
+        if !self.returns.is_empty() {
+            let (return_symbols, actions): (Vec<String>, Vec<Action>) = self
+                .returns
+                .drain()
+                .map(|(return_symbol, map)| (return_symbol, Action::Return(map)))
+                .unzip();
+            self.next_state
+                .insert(0, (RegexParser::from(return_symbols), actions));
+        }
+    }
+
+    fn new_state(&mut self, (nonterminal, rule_index): (usize, usize)) -> State {
+        let state = self.state;
+        self.state_to_rule.insert(state, (nonterminal, rule_index));
+        self.state += 1;
+        state
+    }
+
+    /// Gives the regular expressions that can start the nonterminal, and whether it can match the empty word.
+    fn first_of_nonterminal(&mut self, identifier: usize) -> (Vec<String>, bool) {
+        if let Some(first) = self.first.get(&identifier) {
+            return first.clone();
+        }
+        let grammar = self.grammar;
+        let mut regexes = Vec::new();
+        let mut nullable = false;
+        for rule in &grammar.nonterminals[identifier - 1].rules {
+            let (rule_regexes, rule_nullable) = self.first_of_items(&rule.source.items);
+            regexes.extend(rule_regexes);
+            nullable |= rule_nullable;
+        }
+        self.first.insert(identifier, (regexes.clone(), nullable));
+        (regexes, nullable)
+    }
+
+    fn first_of_items(&mut self, items: &[RuleSourceItem]) -> (Vec<String>, bool) {
+        let mut regexes = Vec::new();
+        for item in items {
+            let (item_regexes, nullable) = self.first_of_item(item);
+            regexes.extend(item_regexes);
+            if !nullable {
+                return (regexes, false);
+            }
+        }
+        (regexes, true)
+    }
+
+    fn first_of_item(&mut self, item: &RuleSourceItem) -> (Vec<String>, bool) {
+        match item {
+            RuleSourceItem::RegexString(s) => (vec![s.clone()], matches_empty(s)),
+            RuleSourceItem::Nested(n) => (vec![n.call_symbol.clone()], false),
+            RuleSourceItem::Identifier(i) => self.first_of_nonterminal(i.source),
+            RuleSourceItem::Repetition(r) => {
+                let (regexes, nullable) = self.first_of_item(&r.item);
+                (regexes, nullable || r.kind != RepetitionKind::OneOrMore)
+            }
+        }
+    }
+
+    /// Gives a regular expression that only matches if one of the given items can start at the input.
+    ///
+    /// The guard is empty if it is the last alternative of a state, as nothing else can match anyway.
+    fn guard(&mut self, items: &[RuleSourceItem], last: bool) -> String {
+        if last {
+            return String::new();
+        }
+        let (regexes, nullable) = self.first_of_items(items);
+        let mut guard = regexes
+            .iter()
+            .map(|r| format!("(?:{r})"))
+            .collect::<Vec<String>>()
+            .join("|");
+        if nullable {
+            guard.push('|');
         }
+        guard
     }
 
-    /// Builds a rule of a gramamr
+    /// Builds a rule of a gramamr, and gives the alternative of the starting state of the nonterminal.
     fn build_rule(
         &mut self,
         (nonterminal, nt_index): Indexed<&Nonterminal>,
         (rule, rule_index): Indexed<&Rule>,
-    ) -> usize {
-        let (mut next_state, mut skip_one) = match rule.source.items.last().unwrap() {
-            RuleSourceItem::Identifier(i) => {
-                if rule.source.items.len() == 1 {
-                    // If the first and only item of a rule is an identifier, skip building that rule.
-                    (0, true)
-                } else {
-                    // If the last rule is an identifier, the next_state of the preceding item will be the starting state of that rule.
-                    (i.source, true)
-                }
-            }
-            // If it is Nested or Regular, don't skip it.
-            _ => (0, false),
-        };
-
+    ) -> (String, Action) {
+        let slots = slots(&rule.source.items);
+        let mut next_state = 0;
         for (item_index, item) in rule.source.items.iter().enumerate().skip(1).rev() {
-            if skip_one {
-                skip_one = false;
-                continue;
-            }
             next_state = self.build_rule_item(
                 next_state,
                 (nonterminal, nt_index),
                 (rule, rule_index),
                 (item, item_index),
+                slots[item_index],
             );
         }
-        next_state
+        let last = rule_index + 1 == nonterminal.rules.len();
+        match rule.source.items.first().unwrap() {
+            RuleSourceItem::Repetition(_) => {
+                // A repetition needs its own state, which is only entered if the rule can start at the input.
+                let guard = self.guard(&rule.source.items, last);
+                let state = self.build_rule_item(
+                    next_state,
+                    (nonterminal, nt_index),
+                    (rule, rule_index),
+                    (&rule.source.items[0], 0),
+                    slots[0],
+                );
+                (guard, Action::Skip(state))
+            }
+            item => self.build_alternative(
+                item,
+                next_state,
+                (nonterminal.identifier, rule_index),
+                slots[0],
+                last,
+            ),
+        }
     }
 
-    /// Builds a grammar rule item.
+    /// Builds the state of a grammar rule item, which continues in the given next state.
     fn build_rule_item(
         &mut self,
         next_state: usize,
-        (nonterminal, nt_index): Indexed<&Nonterminal>,
+        (nonterminal, _): Indexed<&Nonterminal>,
         (_, rule_index): Indexed<&Rule>,
-        (item, item_index): Indexed<&RuleSourceItem>,
+        (item, _): Indexed<&RuleSourceItem>,
+        slot: Slot,
     ) -> usize {
-        let mut next_state = next_state;
-        match item {
-            RuleSourceItem::RegexString(s) => {
-                self.next_state.insert(
-                    self.state,
-                    (
-                        RegexParser::from(vec![s.clone()]),
-                        vec![Action::Next(
-                            next_state,
-                            if next_state <= self.grammar.nonterminals.len() {
-                                vec![(next_state - 1, 0)]
-                            } else {
-                                vec![]
-                            },
-                        )],
-                    ),
-                );
-                next_state = self.state;
+        let rule = (nonterminal.identifier, rule_index);
+        let state = self.new_state(rule);
+        let alternatives = match item {
+            RuleSourceItem::Repetition(r) => {
+                let repeated = Slot {
+                    repeated: r.kind != RepetitionKind::Optional,
+                    ..slot
+                };
+                match r.kind {
+                    RepetitionKind::Optional => vec![
+                        self.build_alternative(&r.item, next_state, rule, repeated, false),
+                        (String::new(), Action::Skip(next_state)),
+                    ],
+                    RepetitionKind::ZeroOrMore => vec![
+                        self.build_alternative(&r.item, state, rule, repeated, false),
+                        (String::new(), Action::Skip(next_state)),
+                    ],
+                    RepetitionKind::OneOrMore => {
+                        // The first occurrence continues in a state that repeats it zero or more times.
+                        let repetition = self.new_state(rule);
+                        let alternatives = vec![
+                            self.build_alternative(&r.item, repetition, rule, repeated, false),
+                            (String::new(), Action::Skip(next_state)),
+                        ];
+                        self.insert_state(repetition, alternatives);
+                        vec![self.build_alternative(&r.item, repetition, rule, repeated, true)]
+                    }
+                }
             }
+            item => vec![self.build_alternative(item, next_state, rule, slot, true)],
+        };
+        self.insert_state(state, alternatives);
+        state
+    }
+
+    /// Builds the alternative that starts the item, after which the given next state is entered.
+    fn build_alternative(
+        &mut self,
+        item: &RuleSourceItem,
+        next_state: State,
+        rule: (usize, usize),
+        slot: Slot,
+        last: bool,
+    ) -> (String, Action) {
+        match item {
+            RuleSourceItem::RegexString(s) => (s.clone(), Action::Next(next_state, slot)),
             RuleSourceItem::Nested(n) => {
-                let test = if item_index == 0 {
-                    nt_index + 1
-                } else {
-                    self.state
+                let point = self.new_state(rule);
+                let call_captures = capture_names(&n.call_symbol).len();
+                let return_slot = Slot {
+                    index: slot.index + call_captures + 1,
+                    ..slot
                 };
-                self.epsilon_rules
-                    .insert((n.return_symbol.clone(), test), next_state);
-                if item_index == 0 {
-                    next_state = n.nonterminal.source;
-                } else {
-                    next_state = self.state;
-                    let index = n.nonterminal.source;
-                    self.next_state.insert(
-                        self.state,
-                        (
-                            RegexParser::from(vec![n.call_symbol.clone()]),
-                            vec![Action::Call(test, index, vec![(index - 1, 0)])],
-                        ),
-                    );
-                }
+                self.returns
+                    .entry(n.return_symbol.clone())
+                    .or_default()
+                    .insert(point, (next_state, return_slot));
+                let node_slot = Slot {
+                    index: slot.index + call_captures,
+                    ..slot
+                };
+                (
+                    n.call_symbol.clone(),
+                    Action::Call(point, n.nonterminal.source, slot, node_slot),
+                )
+            }
+            RuleSourceItem::Identifier(i) => {
+                // Like a nested call, an identifier pushes a return point, which is popped without a return
+                // symbol when the nonterminal is finished, so the identifier can be followed by other items.
+                let point = self.new_state(rule);
+                self.exits.insert(point, next_state);
+                (
+                    self.guard(std::slice::from_ref(item), last),
+                    Action::Enter(point, i.source, slot),
+                )
+            }
+            RuleSourceItem::Repetition(_) => {
+                unreachable!("Repetitions can not be repeated")
             }
-            RuleSourceItem::Identifier(_) => unreachable!("Identifiers are not allowed to be any rule other than the last"),
         }
-        self.state_to_rule
-            .insert(self.state, (nonterminal.identifier, rule_index));
-        self.state += 1;
-        next_state
+    }
+
+    fn insert_state(&mut self, state: State, alternatives: Vec<(String, Action)>) {
+        let (regexes, actions): (Vec<String>, Vec<Action>) = alternatives.into_iter().unzip();
+        self.next_state
+            .insert(state, (RegexParser::from(regexes), actions));
     }
 
     /// Build all rules of a nonterminal
     fn build_nonterminal(&mut self, nt_index: usize, nonterminal: &Nonterminal) {
-        let mut next_states: Vec<State> = Vec::new();
-        for (rule_index, rule) in nonterminal.rules.iter().enumerate() {
-            next_states.push(self.build_rule((nonterminal, nt_index), (rule, rule_index)))
-        }
         let starting_regexes: Vec<(String, Action)> = nonterminal
             .rules
             .iter()
-            .map(|rule| rule.source.items.get(0).unwrap())
             .enumerate()
-            .flat_map(|(rule_index, reg)| match reg {
-                RuleSourceItem::RegexString(s) => {
-                    let next_state = next_states[rule_index];
-                    if next_state <= self.grammar.nonterminals.len() && next_state != 0 {
-                        vec![(
-                            s.clone(),
-                            Action::Next(next_state, vec![(next_state - 1, 0)]),
-                        )]
-                    } else {
-                        vec![(s.clone(), Action::Next(next_state, vec![]))]
-                    }
-                }
-                RuleSourceItem::Nested(n) => {
-                    let index = n.nonterminal.source;
-                    vec![(
-                        n.call_symbol.clone(),
-                        Action::Call(index + 1, index, vec![(index - 1, 0)]),
-                    )]
-                }
-                RuleSourceItem::Identifier(id) => {
-                    let index = id.source;
-                    self.collect_starting_rules(index - 1, rule_index)
-                }
-            })
+            .map(|(rule_index, rule)| self.build_rule((nonterminal, nt_index), (rule, rule_index)))
             .collect();
-        self.next_state.insert(
-            nonterminal.identifier,
-            (
-                RegexParser::from(
-                    starting_regexes
-                        .iter()
-                        .map(|x| x.0.clone())
-                        .collect::<Vec<String>>(),
-                ),
-                starting_regexes.iter().map(|x| x.1.clone()).collect(),
-            ),
-        );
+        self.insert_state(nonterminal.identifier, starting_regexes);
     }
 }
 
-fn add_id_to_action(action: Action, identifier: usize, rule_nr: usize) -> Action {
-    let edited_action = match action {
-        Action::Call(a, b, id) => {
-            let mut new_id = id;
-            new_id.push((identifier, rule_nr));
-            Action::Call(a, b, new_id)
-        }
-        Action::Next(a, id) => {
-            let mut new_id = id;
-            new_id.push((identifier, rule_nr));
-            Action::Next(a, new_id)
-        }
-        Action::Return(_) => {
-            unreachable!("Return action should not be present in the first rule of a word")
+/// Gives the position of the first child of every item in the parse tree node of its rule.
+fn slots(items: &[RuleSourceItem]) -> Vec<Slot> {
+    fn size(item: &RuleSourceItem) -> usize {
+        match item {
+            RuleSourceItem::RegexString(s) => capture_names(s).len(),
+            RuleSourceItem::Nested(n) => {
+                capture_names(&n.call_symbol).len() + 1 + capture_names(&n.return_symbol).len()
+            }
+            RuleSourceItem::Identifier(_) => 1,
+            RuleSourceItem::Repetition(r) => size(&r.item),
         }
-    };
-    edited_action
-}
\ No newline at end of file
+    }
+    let mut index = 0;
+    items
+        .iter()
+        .map(|item| {
+            let slot = Slot {
+                index,
+                repeated: false,
+            };
+            index += size(item);
+            slot
+        })
+        .collect()
+}
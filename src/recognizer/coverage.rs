@@ -0,0 +1,281 @@
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+use super::{Recognizer, State};
+
+/// The number of times the rules and states of a recognizer were used, over all inputs since it started recording.
+///
+/// A rule is used when its nonterminal chooses it at the start, and a state is used when a transition leaves it, so
+/// the inputs that are rejected are counted up to where they are rejected. The rules are numbered from 1, like in the
+/// errors of the elaborator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coverage {
+    names: Vec<String>,
+    /// The number of uses of every rule of every nonterminal.
+    rules: Vec<Vec<usize>>,
+    /// The number of uses of every state, with the nonterminal and rule index it is part of.
+    states: BTreeMap<State, (usize, Option<(usize, usize)>)>,
+}
+
+/// The coverage that a recognizer records, which the runs on all threads add to.
+#[derive(Debug)]
+pub(crate) struct Recording(Mutex<Coverage>);
+
+impl Recording {
+    /// Gives the coverage, also if a run panicked while it added to it, as an addition can not be left halfway.
+    pub(crate) fn lock(&self) -> MutexGuard<'_, Coverage> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub(crate) fn record(&self, state: State, alternative: usize) {
+        self.lock().record(state, alternative);
+    }
+}
+
+impl Clone for Recording {
+    fn clone(&self) -> Self {
+        Recording(Mutex::new(self.lock().clone()))
+    }
+}
+
+impl Recognizer {
+    /// Starts recording which rules and states are used by [`Recognizer::parse`] and [`Recognizer::recognize`].
+    ///
+    /// The runs on all threads that share the recognizer are recorded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vpl_parser_generator::Recognizer;
+    ///
+    /// let grammar = "Stmt:\n  Expr=E \";\" -> E\nExpr:\n  \"[0-9]+\" -> \"n\"\n  [\"\\(\" Expr=E \"\\)\"] -> E\n  \"x\" -> \"x\"";
+    /// let mut recognizer = Recognizer::new(grammar).unwrap();
+    /// recognizer.record_coverage();
+    /// recognizer.recognize("((1));");
+    /// recognizer.recognize("2;");
+    /// let coverage = recognizer.coverage().unwrap();
+    /// assert_eq!(coverage.rule_uses("Expr"), Some(vec![2, 2, 0]));
+    /// assert_eq!(coverage.unused_rules(), vec![("Expr".to_string(), 3)]);
+    /// ```
+    pub fn record_coverage(&mut self) {
+        let rules = (1..=self.names.len())
+            .map(|state| {
+                let alternatives = self.next_state.get(&state).map_or(0, |(regex, _)| regex.alternatives.len());
+                vec![0; alternatives]
+            })
+            .collect();
+        let states = self
+            .next_state
+            .keys()
+            .map(|state| {
+                let rule = self.state_to_rule.get(state).map(|(nonterminal, rule)| (nonterminal - 1, *rule));
+                (*state, (0, rule))
+            })
+            .collect();
+        let coverage = Coverage {
+            names: self.names.to_vec(),
+            rules,
+            states,
+        };
+        self.coverage = Some(Box::new(Recording(Mutex::new(coverage))));
+    }
+
+    /// Gives a copy of the coverage recorded since [`Recognizer::record_coverage`], if it is recording.
+    pub fn coverage(&self) -> Option<Coverage> {
+        self.coverage.as_ref().map(|recording| recording.lock().clone())
+    }
+
+    /// Stops recording, and gives the recorded coverage.
+    pub fn take_coverage(&mut self) -> Option<Coverage> {
+        let recording = self.coverage.take()?;
+        Some(recording.0.into_inner().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Gives the recognizer without its recording, for runs that are not part of the coverage, like the checks of
+    /// the inputs that analyses of the grammar find. A recording recognizer is cloned, which shares its automaton.
+    pub(crate) fn without_coverage(&self) -> Cow<'_, Recognizer> {
+        match self.coverage {
+            Some(_) => Cow::Owned(Recognizer {
+                coverage: None,
+                ..self.clone()
+            }),
+            None => Cow::Borrowed(self),
+        }
+    }
+}
+
+impl Coverage {
+    /// Counts a transition that leaves the state, which chooses a rule if it is the starting state of a nonterminal.
+    pub(crate) fn record(&mut self, state: State, alternative: usize) {
+        if let Some((uses, _)) = self.states.get_mut(&state) {
+            *uses += 1;
+        }
+        if state > 0 && state <= self.rules.len() {
+            if let Some(uses) = self.rules[state - 1].get_mut(alternative) {
+                *uses += 1;
+            }
+        }
+    }
+
+    /// Adds the uses of another coverage of the same grammar, for example of a recognizer in another process.
+    pub fn merge(&mut self, other: &Coverage) {
+        for (uses, other_uses) in self.rules.iter_mut().flatten().zip(other.rules.iter().flatten()) {
+            *uses += other_uses;
+        }
+        for (state, (uses, _)) in &mut self.states {
+            *uses += other.states.get(state).map_or(0, |(other_uses, _)| *other_uses);
+        }
+    }
+
+    /// Gives the number of uses of every rule of a nonterminal.
+    pub fn rule_uses(&self, nonterminal: &str) -> Option<Vec<usize>> {
+        let index = self.names.iter().position(|name| name == nonterminal)?;
+        Some(self.rules[index].clone())
+    }
+
+    /// Gives the rules that were never used, with their nonterminal.
+    pub fn unused_rules(&self) -> Vec<(String, usize)> {
+        self.names
+            .iter()
+            .zip(&self.rules)
+            .flat_map(|(name, rules)| {
+                rules
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, uses)| **uses == 0)
+                    .map(move |(rule, _)| (name.clone(), rule + 1))
+            })
+            .collect()
+    }
+
+    /// Gives the states that were used at most the given number of times, with their number of uses.
+    pub fn rare_states(&self, threshold: usize) -> Vec<(State, usize)> {
+        self.states
+            .iter()
+            .filter(|(_, (uses, _))| *uses <= threshold)
+            .map(|(state, (uses, _))| (*state, *uses))
+            .collect()
+    }
+
+    /// Writes a report of the rules that were never used, and the states that were used at most the given number of
+    /// times.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vpl_parser_generator::Recognizer;
+    ///
+    /// let mut recognizer = Recognizer::new("A:\n  \"a\" -> \"a\"\n  [\"\\(\" A=A \"\\)\"] -> A").unwrap();
+    /// recognizer.record_coverage();
+    /// recognizer.recognize("a");
+    /// let report = recognizer.coverage().unwrap().report(0);
+    /// assert!(report.starts_with("1 of 2 rules used\n"));
+    /// assert!(report.contains("Rule 2 of word A is never used"));
+    /// ```
+    pub fn report(&self, threshold: usize) -> String {
+        let total: usize = self.rules.iter().map(Vec::len).sum();
+        let unused = self.unused_rules();
+        let mut report = format!("{} of {total} rules used\n", total - unused.len());
+        for (name, rule) in &unused {
+            writeln!(report, "Rule {rule} of word {name} is never used").unwrap();
+        }
+        let rare = self.rare_states(threshold);
+        if !rare.is_empty() {
+            writeln!(report, "States used at most {threshold} times:").unwrap();
+        }
+        for (state, uses) in rare {
+            let rule = match self.states[&state].1 {
+                Some((nonterminal, rule)) => format!("rule {} of word {}", rule + 1, self.names[nonterminal]),
+                None if state > 0 && state <= self.names.len() => format!("start of word {}", self.names[state - 1]),
+                None => "end of a word".to_string(),
+            };
+            writeln!(report, "  state {state} ({rule}): {uses}").unwrap();
+        }
+        report
+    }
+
+    /// Exports the uses of the rules in the lcov format, for the grammar file at the path with the given text.
+    ///
+    /// Every nonterminal is a function, which is used as often as its rules together, and every rule is a line.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vpl_parser_generator::Recognizer;
+    ///
+    /// let grammar = "A:\n  \"a\" -> \"a\"\n  \"b\" -> \"b\"\n";
+    /// let mut recognizer = Recognizer::new(grammar).unwrap();
+    /// recognizer.record_coverage();
+    /// recognizer.recognize("b");
+    /// let lcov = recognizer.coverage().unwrap().to_lcov("a.vpa", grammar);
+    /// assert!(lcov.contains("SF:a.vpa\nFN:1,A\nFNDA:1,A\n"));
+    /// assert!(lcov.contains("DA:2,0\nDA:3,1\nLF:2\nLH:1\n"));
+    /// ```
+    pub fn to_lcov(&self, path: &str, grammar: &str) -> String {
+        let lines = rule_lines(grammar);
+        let mut lcov = format!("TN:\nSF:{path}\n");
+        for (name, rules) in self.names.iter().zip(&self.rules) {
+            if let Some((definition, _)) = lines.get(name) {
+                writeln!(lcov, "FN:{},{name}", definition + 1).unwrap();
+                writeln!(lcov, "FNDA:{},{name}", rules.iter().sum::<usize>()).unwrap();
+            }
+        }
+        let used = self.rules.iter().filter(|rules| rules.iter().any(|uses| *uses > 0)).count();
+        writeln!(lcov, "FNF:{}\nFNH:{used}", self.names.len()).unwrap();
+        let mut found = 0;
+        let mut hit = 0;
+        for (name, rules) in self.names.iter().zip(&self.rules) {
+            let Some((_, rule_lines)) = lines.get(name) else {
+                continue;
+            };
+            for (line, uses) in rule_lines.iter().zip(rules) {
+                writeln!(lcov, "DA:{},{uses}", line + 1).unwrap();
+                found += 1;
+                hit += usize::from(*uses > 0);
+            }
+        }
+        writeln!(lcov, "LF:{found}\nLH:{hit}\nend_of_record").unwrap();
+        lcov
+    }
+}
+
+/// Gives the line of the definition of every nonterminal and the lines of its rules, counted from 0, as a rule is a
+/// line after the definition.
+fn rule_lines(grammar: &str) -> BTreeMap<String, (usize, Vec<usize>)> {
+    let mut lines: BTreeMap<String, (usize, Vec<usize>)> = BTreeMap::new();
+    let mut current = None;
+    for (number, line) in grammar.lines().enumerate() {
+        if line.starts_with(char::is_alphanumeric) {
+            let name = line.split(':').next().unwrap_or_default().trim().to_string();
+            lines.insert(name.clone(), (number, Vec::new()));
+            current = Some(name);
+        } else if let Some(name) = current.as_ref().filter(|_| !line.trim().is_empty()) {
+            lines.get_mut(name).unwrap().1.push(number);
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{borrow::Cow, sync::Arc};
+
+    use crate::Recognizer;
+
+    #[test]
+    fn recognizers_without_coverage_share_the_automaton() {
+        let mut recognizer = Recognizer::new("A:\n  \"a\" -> \"x\"").unwrap();
+        assert!(matches!(recognizer.without_coverage(), Cow::Borrowed(_)));
+        recognizer.record_coverage();
+        let without = recognizer.without_coverage();
+        assert!(without.coverage.is_none());
+        assert!(Arc::ptr_eq(&without.next_state, &recognizer.next_state));
+        assert!(Arc::ptr_eq(&without.state_to_rule, &recognizer.state_to_rule));
+        assert_eq!(without.recognize("a"), Some(()));
+        assert_eq!(recognizer.coverage().unwrap().rule_uses("A"), Some(vec![0]));
+    }
+}
@@ -0,0 +1,643 @@
+//! Emptiness, inclusion and equivalence of recognizers.
+//!
+//! The automaton of a recognizer is decided as a visibly pushdown automaton over its symbols: a transition reads
+//! any string that its regular expression matches entirely, and the alternatives of a state are not ordered, so
+//! guards are ignored. The symbols of both automata are split in the sets of regular expressions that match the
+//! same non-empty string, which are the letters the automata are run on together. An identifier is entered without reading a
+//! symbol, so a word that can contain itself through identifiers only, without a nested call in between, can give
+//! a language that is not visibly pushdown, and is refused. Identifiers at the end of a rule are left at the end
+//! of the word they are in, so these can be recursive.
+//!
+//! Inclusion is decided on the fly by running the first automaton together with the subset construction of the
+//! second, and searching a shortest word that the first accepts and the second does not, like Dijkstra's algorithm
+//! generalized to summaries of nested words. A counterexample is confirmed by running the recognizers on it, as
+//! they read the input with the first matching alternative, which can split it in other symbols. The second
+//! automaton is only exact if the recognizer can not take another alternative than the automaton, so a state of
+//! the second recognizer whose alternatives can match the start of the same input is refused.
+
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    error::Error,
+};
+
+use super::{recognizer_automaton::Action, Recognizer, State};
+use crate::{
+    elaborator::SymbolKind,
+    regex::{intersection, matches_empty, minterms},
+};
+
+/// A state of a recognizer, with the return points of the identifiers that were entered since the last call.
+type Extended = (State, Vec<State>);
+
+impl Recognizer {
+    /// Gives a shortest input that the recognizer accepts, or `None` if it accepts no input.
+    ///
+    /// This gives an error if the automaton is not a visibly pushdown automaton over its symbols, or if the
+    /// shortest input of the automaton is read differently by the recognizer. See [`Recognizer::is_subset_of`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vpl_parser_generator::Recognizer;
+    ///
+    /// let endless = Recognizer::new("A:\n  [\"\\(\" A=A \"\\)\"] -> A").unwrap();
+    /// assert_eq!(endless.shortest_input().unwrap(), None);
+    /// let nested = Recognizer::new("A:\n  [\"\\(\" A=A \"\\)\"] -> A\n  \"a\" -> \"a\"").unwrap();
+    /// assert_eq!(nested.shortest_input().unwrap(), Some("a".to_string()));
+    /// ```
+    pub fn shortest_input(&self) -> Result<Option<String>, Box<dyn Error>> {
+        counterexample(self, None)
+    }
+
+    /// Checks if every input accepted by this recognizer is accepted by the other, or gives a shortest input that
+    /// is only accepted by this one.
+    ///
+    /// The automata are compared over their symbols, where a transition reads any string its regular expression
+    /// matches. This is exact if the recognizers read every input in the same symbols as the automata, which is
+    /// when no symbols overlap (see [`crate::check_alphabet`]) and every state can tell its alternatives apart by
+    /// the next symbol. A counterexample is checked with the recognizers, and gives an error if they do not agree.
+    /// An error is also given if two alternatives of a state of the other recognizer can match the start of the same
+    /// input, as it only takes the first, if a string is a symbol of a different kind in both automata, or if a word
+    /// can contain itself through identifiers only. An alternative that matches only the empty string, like the end
+    /// of a repetition, is not compared, so a repetition that is followed by what it repeats is read as often as
+    /// the automaton can.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vpl_parser_generator::Recognizer;
+    ///
+    /// let a = Recognizer::new("A:\n  [\"\\(\" A=A \"\\)\"] -> A\n  \"a\" -> \"a\"").unwrap();
+    /// let ab = Recognizer::new("A:\n  [\"\\(\" A=A \"\\)\"] -> A\n  \"a\" -> \"a\"\n  \"b\" -> \"b\"").unwrap();
+    /// assert_eq!(a.is_subset_of(&ab).unwrap(), None);
+    /// assert_eq!(ab.is_subset_of(&a).unwrap(), Some("b".to_string()));
+    /// ```
+    pub fn is_subset_of(&self, other: &Recognizer) -> Result<Option<String>, Box<dyn Error>> {
+        counterexample(self, Some(other))
+    }
+
+    /// Checks if both recognizers accept the same inputs, or gives a shortest input that only one of them accepts.
+    ///
+    /// This decides inclusion both ways, see [`Recognizer::is_subset_of`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vpl_parser_generator::Recognizer;
+    ///
+    /// let before = Recognizer::new("A:\n  [\"\\(\" A=A \"\\)\"] -> A\n  \"a\" -> \"a\"").unwrap();
+    /// let after = Recognizer::new("A:\n  [\"\\(\" A=A \"\\)\"] -> A\n  B=B -> B\nB:\n  \"a\" -> \"a\"").unwrap();
+    /// assert_eq!(before.equivalent(&after).unwrap(), None);
+    /// let changed = Recognizer::new("A:\n  [\"\\(\" A=A \"\\)\"] -> A\n  \"a+\" -> \"a\"").unwrap();
+    /// assert_eq!(before.equivalent(&changed).unwrap(), Some("aa".to_string()));
+    /// ```
+    pub fn equivalent(&self, other: &Recognizer) -> Result<Option<String>, Box<dyn Error>> {
+        match (counterexample(self, Some(other)), counterexample(other, Some(self))) {
+            (Ok(Some(first)), Ok(Some(second))) if second.len() < first.len() => Ok(Some(second)),
+            (Ok(Some(word)), _) | (_, Ok(Some(word))) => Ok(Some(word)),
+            (Err(error), _) | (_, Err(error)) => Err(error),
+            (Ok(None), Ok(None)) => Ok(None),
+        }
+    }
+}
+
+/// Gives a shortest input that the first recognizer accepts and the second does not, if there is one.
+fn counterexample(first: &Recognizer, second: Option<&Recognizer>) -> Result<Option<String>, Box<dyn Error>> {
+    let mut symbols = Vec::new();
+    let first_automaton = Automaton::new(first, &mut symbols)?;
+    if let Some(second) = second {
+        overlapping_alternatives(second)?;
+    }
+    let second_automaton = second.map(|second| Automaton::new(second, &mut symbols)).transpose()?;
+    let letters = letters(&symbols, second.is_some())?;
+    let Some(word) = Search::new(&first_automaton, second_automaton.as_ref(), &symbols, &letters).shortest() else {
+        return Ok(None);
+    };
+    let accepted = first.without_coverage().recognize(&word).is_some();
+    let rejected = second.is_none_or(|second| second.without_coverage().recognize(&word).is_none());
+    if !(accepted && rejected) {
+        return Err(format!(
+            "Input {word:?} is a counterexample for the symbols of the automata, but not for the recognizers, which read it in other symbols"
+        )
+        .into());
+    }
+    Ok(Some(word))
+}
+
+/// Checks that no state of a recognizer has two alternatives that can match the start of the same input, as the
+/// recognizer takes the first where the automaton could take both. Alternatives that only match the empty string are
+/// left out.
+fn overlapping_alternatives(recognizer: &Recognizer) -> Result<(), String> {
+    let mut states: Vec<&State> = recognizer.next_state.keys().collect();
+    states.sort();
+    for state in states {
+        let alternatives = &recognizer.next_state[state].0.alternatives;
+        for (i, first) in alternatives.iter().enumerate().filter(|(_, regex)| !regex.is_empty()) {
+            for second in alternatives[i + 1..].iter().filter(|regex| !regex.is_empty()) {
+                if let Some(witness) = intersection(&format!("(?:{first})(?s:.*)"), &format!("(?:{second})(?s:.*)")) {
+                    let name = match recognizer.state_to_rule.get(state) {
+                        Some((nonterminal, _)) => &recognizer.names[nonterminal - 1],
+                        None if *state == 0 => "the end of a word",
+                        None => &recognizer.names[state - 1],
+                    };
+                    return Err(format!(
+                        "Alternatives {first:?} and {second:?} in {name} both match the start of input {witness:?}, and the recognizer only takes the first, so the automata can not be compared"
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A set of symbols that match the same strings, with a shortest such string.
+struct Letter {
+    symbols: Vec<usize>,
+    witness: String,
+}
+
+/// Splits the symbols in letters, and checks that the symbols of a letter have the same kind if the automata are
+/// compared.
+fn letters(symbols: &[(SymbolKind, String)], compared: bool) -> Result<Vec<Letter>, String> {
+    let regexes: Vec<&str> = symbols.iter().map(|(_, regex)| regex.as_str()).collect();
+    let mut letters = Vec::new();
+    for (symbols_of_letter, witness) in minterms(&regexes)? {
+        let first = symbols[symbols_of_letter[0]].0;
+        let other = symbols_of_letter.iter().map(|symbol| symbols[*symbol].0).find(|kind| *kind != first);
+        if let (true, Some(other)) = (compared, other) {
+            return Err(format!(
+                "Input {witness:?} is read as both {first} and {other} symbol, so the automata can not be compared"
+            ));
+        }
+        letters.push(Letter {
+            symbols: symbols_of_letter,
+            witness,
+        });
+    }
+    Ok(letters)
+}
+
+/// The automaton of a recognizer as a visibly pushdown automaton over the symbols, whose states are the extended
+/// states of the recognizer. State 0 is the starting state.
+struct Automaton {
+    internals: Vec<Vec<(usize, usize)>>,
+    /// The calls of every state, with their symbol, the stack symbol they push and the next state.
+    calls: Vec<Vec<(usize, usize, usize)>>,
+    /// The returns of every state, with their symbol, the stack symbol they pop and the next state.
+    returns: Vec<Vec<(usize, usize, usize)>>,
+    epsilons: Vec<Vec<usize>>,
+    /// The states that can be reached from every state without reading a symbol, including itself.
+    closures: Vec<Vec<usize>>,
+    accepting: Option<usize>,
+}
+
+/// Builds an [`Automaton`] by exploring the extended states that can be reached from the start.
+struct AutomatonBuilder<'a> {
+    recognizer: &'a Recognizer,
+    symbols: &'a mut Vec<(SymbolKind, String)>,
+    automaton: Automaton,
+    states: Vec<Extended>,
+    ids: HashMap<Extended, usize>,
+    stack_symbols: HashMap<Extended, usize>,
+}
+
+impl Automaton {
+    fn new(recognizer: &Recognizer, symbols: &mut Vec<(SymbolKind, String)>) -> Result<Self, String> {
+        let mut builder = AutomatonBuilder {
+            recognizer,
+            symbols,
+            automaton: Automaton {
+                internals: Vec::new(),
+                calls: Vec::new(),
+                returns: Vec::new(),
+                epsilons: Vec::new(),
+                closures: Vec::new(),
+                accepting: None,
+            },
+            states: Vec::new(),
+            ids: HashMap::new(),
+            stack_symbols: HashMap::new(),
+        };
+        builder.state((1, Vec::new()));
+        let mut explored = 0;
+        while explored < builder.states.len() {
+            builder.explore(explored)?;
+            explored += 1;
+        }
+        let mut automaton = builder.automaton;
+        automaton.accepting = builder.ids.get(&(0, Vec::new())).copied();
+        automaton.closures = (0..automaton.epsilons.len())
+            .map(|state| automaton.closure(state))
+            .collect();
+        Ok(automaton)
+    }
+
+    fn closure(&self, state: usize) -> Vec<usize> {
+        let mut closure = vec![state];
+        let mut index = 0;
+        while index < closure.len() {
+            for next in &self.epsilons[closure[index]] {
+                if !closure.contains(next) {
+                    closure.push(*next);
+                }
+            }
+            index += 1;
+        }
+        closure.sort();
+        closure
+    }
+}
+
+impl AutomatonBuilder<'_> {
+    fn state(&mut self, state: Extended) -> usize {
+        if let Some(id) = self.ids.get(&state) {
+            return *id;
+        }
+        let id = self.states.len();
+        self.ids.insert(state.clone(), id);
+        self.states.push(state);
+        self.automaton.internals.push(Vec::new());
+        self.automaton.calls.push(Vec::new());
+        self.automaton.returns.push(Vec::new());
+        self.automaton.epsilons.push(Vec::new());
+        id
+    }
+
+    fn symbol(&mut self, kind: SymbolKind, regex: &str) -> usize {
+        let position = self
+            .symbols
+            .iter()
+            .position(|(other_kind, other)| *other_kind == kind && other == regex);
+        position.unwrap_or_else(|| {
+            self.symbols.push((kind, regex.to_string()));
+            self.symbols.len() - 1
+        })
+    }
+
+    /// Gives the stack symbol of a call, and adds the returns that pop it from the end state.
+    fn stack_symbol(&mut self, point: State, entered: Vec<State>) -> usize {
+        if let Some(id) = self.stack_symbols.get(&(point, entered.clone())) {
+            return *id;
+        }
+        let id = self.stack_symbols.len();
+        self.stack_symbols.insert((point, entered.clone()), id);
+        let end = self.state((0, Vec::new()));
+        let Some((regex, actions)) = self.recognizer.next_state.get(&0) else {
+            return id;
+        };
+        for (alternative, action) in regex.alternatives.iter().zip(actions) {
+            if let Action::Return(map) = action {
+                if let Some((next, _)) = map.get(&point) {
+                    let symbol = self.symbol(SymbolKind::Return, alternative);
+                    let next = self.state((*next, entered.clone()));
+                    self.automaton.returns[end].push((symbol, id, next));
+                }
+            }
+        }
+        id
+    }
+
+    fn explore(&mut self, id: usize) -> Result<(), String> {
+        let (state, entered) = self.states[id].clone();
+        if state == 0 {
+            // The end of an entered word continues after its identifier, the returns are added with the calls.
+            if let Some((point, rest)) = entered.split_last() {
+                let next = self.state((self.recognizer.exits[point], rest.to_vec()));
+                self.automaton.epsilons[id].push(next);
+            }
+            return Ok(());
+        }
+        let Some((regex, actions)) = self.recognizer.next_state.get(&state) else {
+            return Ok(());
+        };
+        for (alternative, action) in regex.alternatives.iter().zip(actions) {
+            match action {
+                Action::Call(point, next, _, _) => {
+                    let symbol = self.symbol(SymbolKind::Call, alternative);
+                    let stack_symbol = self.stack_symbol(*point, entered.clone());
+                    let next = self.state((*next, Vec::new()));
+                    self.automaton.calls[id].push((symbol, stack_symbol, next));
+                }
+                Action::Enter(point, next, _) => {
+                    if entered.contains(point) {
+                        return Err(format!(
+                            "Word {} can contain itself without a nested call in between, so its language may not be visibly pushdown",
+                            self.recognizer.names[next - 1]
+                        ));
+                    }
+                    // An identifier at the end of a rule continues at the end state, like the word it enters.
+                    let mut entered = entered.clone();
+                    if self.recognizer.exits[point] != 0 {
+                        entered.push(*point);
+                    }
+                    let next = self.state((*next, entered));
+                    self.automaton.epsilons[id].push(next);
+                }
+                Action::Next(next, _) => {
+                    let symbol = self.symbol(SymbolKind::Internal, alternative);
+                    let next = self.state((*next, entered.clone()));
+                    self.automaton.internals[id].push((symbol, next));
+                    // A regular expression that matches the empty string can also be read without a symbol.
+                    if matches_empty(alternative) {
+                        self.automaton.epsilons[id].push(next);
+                    }
+                }
+                Action::Skip(next) => {
+                    let next = self.state((*next, entered.clone()));
+                    self.automaton.epsilons[id].push(next);
+                }
+                Action::Return(_) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The subset construction of an automaton, whose states are the pairs of a state at the start of the current
+/// nesting level and a state that can be reached from it.
+struct Subsets<'a> {
+    automaton: &'a Automaton,
+    letters: &'a [Letter],
+    sets: Vec<Vec<(usize, usize)>>,
+    ids: HashMap<Vec<(usize, usize)>, usize>,
+}
+
+impl Subsets<'_> {
+    fn start(&mut self) -> usize {
+        self.set(vec![(0, 0)])
+    }
+
+    /// Gives the state of a set of pairs, after adding the states that can be reached without reading a symbol.
+    fn set(&mut self, pairs: Vec<(usize, usize)>) -> usize {
+        let mut pairs: Vec<(usize, usize)> = pairs
+            .into_iter()
+            .flat_map(|(start, state)| self.automaton.closures[state].iter().map(move |next| (start, *next)))
+            .collect();
+        pairs.sort();
+        pairs.dedup();
+        if let Some(id) = self.ids.get(&pairs) {
+            return *id;
+        }
+        self.ids.insert(pairs.clone(), self.sets.len());
+        self.sets.push(pairs);
+        self.sets.len() - 1
+    }
+
+    fn reads(&self, letter: usize, symbol: usize) -> bool {
+        self.letters[letter].symbols.binary_search(&symbol).is_ok()
+    }
+
+    fn internal(&mut self, set: usize, letter: usize) -> usize {
+        let mut pairs = Vec::new();
+        for (start, state) in &self.sets[set] {
+            for (symbol, next) in &self.automaton.internals[*state] {
+                if self.reads(letter, *symbol) {
+                    pairs.push((*start, *next));
+                }
+            }
+        }
+        self.set(pairs)
+    }
+
+    fn call(&mut self, set: usize, letter: usize) -> usize {
+        let mut pairs = Vec::new();
+        for (_, state) in &self.sets[set] {
+            for (symbol, _, next) in &self.automaton.calls[*state] {
+                if self.reads(letter, *symbol) {
+                    pairs.push((*next, *next));
+                }
+            }
+        }
+        self.set(pairs)
+    }
+
+    /// Gives the state after a return, from the state before the call and the state at the end of the nested word.
+    fn ret(&mut self, caller: usize, call: usize, nested: usize, letter: usize) -> usize {
+        let mut pairs = Vec::new();
+        for (start, state) in &self.sets[caller] {
+            for (call_symbol, stack_symbol, entry) in &self.automaton.calls[*state] {
+                if !self.reads(call, *call_symbol) {
+                    continue;
+                }
+                for (_, end) in self.sets[nested].iter().filter(|(nested_start, _)| nested_start == entry) {
+                    for (symbol, popped, next) in &self.automaton.returns[*end] {
+                        if popped == stack_symbol && self.reads(letter, *symbol) {
+                            pairs.push((*start, *next));
+                        }
+                    }
+                }
+            }
+        }
+        self.set(pairs)
+    }
+
+    fn accepts(&self, set: usize) -> bool {
+        self.automaton
+            .accepting
+            .is_some_and(|accepting| self.sets[set].contains(&(0, accepting)))
+    }
+}
+
+/// A state of the product at the start of a nesting level, and a state of the product that is reached from it by
+/// a nested word.
+type Item = (usize, usize);
+
+/// How the shortest word of an item is made.
+#[derive(Clone, Copy)]
+enum Derivation {
+    Start,
+    Epsilon(Item),
+    Internal(Item, usize),
+    /// The word of the item before the call, the call letter, the nested word and the return letter.
+    Nested(Item, usize, Item, usize),
+}
+
+/// Searches a shortest word that the first automaton accepts and the second does not, in the order of the length
+/// of the words of the items.
+struct Search<'a> {
+    first: &'a Automaton,
+    second: Option<Subsets<'a>>,
+    letters: &'a [Letter],
+    letters_of: Vec<Vec<usize>>,
+    /// The states of the product, a state of the first automaton and a state of the subset construction.
+    states: Vec<(usize, usize)>,
+    ids: HashMap<(usize, usize), usize>,
+    queue: BinaryHeap<Reverse<(usize, Item)>>,
+    found: HashMap<Item, (usize, Derivation)>,
+    done: HashMap<Item, (usize, Derivation)>,
+    /// The items with a call to every start of a nesting level, with the call letter and stack symbol.
+    callers: HashMap<usize, Vec<(Item, usize, usize)>>,
+    /// The states that are done for every start of a nesting level.
+    ends: HashMap<usize, Vec<usize>>,
+}
+
+impl<'a> Search<'a> {
+    fn new(
+        first: &'a Automaton,
+        second: Option<&'a Automaton>,
+        symbols: &[(SymbolKind, String)],
+        letters: &'a [Letter],
+    ) -> Self {
+        let mut letters_of = vec![Vec::new(); symbols.len()];
+        for (index, letter) in letters.iter().enumerate() {
+            for symbol in &letter.symbols {
+                letters_of[*symbol].push(index);
+            }
+        }
+        Search {
+            first,
+            second: second.map(|automaton| Subsets {
+                automaton,
+                letters,
+                sets: Vec::new(),
+                ids: HashMap::new(),
+            }),
+            letters,
+            letters_of,
+            states: Vec::new(),
+            ids: HashMap::new(),
+            queue: BinaryHeap::new(),
+            found: HashMap::new(),
+            done: HashMap::new(),
+            callers: HashMap::new(),
+            ends: HashMap::new(),
+        }
+    }
+
+    fn state(&mut self, state: (usize, usize)) -> usize {
+        *self.ids.entry(state).or_insert_with(|| {
+            self.states.push(state);
+            self.states.len() - 1
+        })
+    }
+
+    fn push(&mut self, item: Item, length: usize, derivation: Derivation) {
+        if self.done.contains_key(&item) || self.found.get(&item).is_some_and(|(found, _)| *found <= length) {
+            return;
+        }
+        self.found.insert(item, (length, derivation));
+        self.queue.push(Reverse((length, item)));
+    }
+
+    fn length(&self, letter: usize) -> usize {
+        self.letters[letter].witness.len()
+    }
+
+    fn shortest(mut self) -> Option<String> {
+        let second_start = self.second.as_mut().map_or(0, |second| second.start());
+        let start = self.state((0, second_start));
+        self.push((start, start), 0, Derivation::Start);
+        while let Some(Reverse((length, item))) = self.queue.pop() {
+            if self.done.contains_key(&item) {
+                continue;
+            }
+            let derivation = self.found[&item].1;
+            self.done.insert(item, (length, derivation));
+            let (entry, state) = item;
+            let (first_state, second_state) = self.states[state];
+            let rejected = self.second.as_ref().is_none_or(|second| !second.accepts(second_state));
+            if entry == start && self.first.accepting == Some(first_state) && rejected {
+                return Some(self.word(item));
+            }
+            for next in self.first.epsilons[first_state].clone() {
+                let next = self.state((next, second_state));
+                self.push((entry, next), length, Derivation::Epsilon(item));
+            }
+            for (symbol, next) in self.first.internals[first_state].clone() {
+                for letter in self.letters_of[symbol].clone() {
+                    let second_next = self.second.as_mut().map_or(0, |second| second.internal(second_state, letter));
+                    let next = self.state((next, second_next));
+                    self.push((entry, next), length + self.length(letter), Derivation::Internal(item, letter));
+                }
+            }
+            for (symbol, stack_symbol, next) in self.first.calls[first_state].clone() {
+                for letter in self.letters_of[symbol].clone() {
+                    let second_next = self.second.as_mut().map_or(0, |second| second.call(second_state, letter));
+                    let nested = self.state((next, second_next));
+                    self.push((nested, nested), 0, Derivation::Start);
+                    let caller = (item, letter, stack_symbol);
+                    self.callers.entry(nested).or_default().push(caller);
+                    for end in self.ends.get(&nested).cloned().unwrap_or_default() {
+                        self.combine(caller, (nested, end));
+                    }
+                }
+            }
+            self.ends.entry(entry).or_default().push(state);
+            for caller in self.callers.get(&entry).cloned().unwrap_or_default() {
+                self.combine(caller, item);
+            }
+        }
+        None
+    }
+
+    /// Adds the items after the returns from a nested word to the item that called it.
+    fn combine(&mut self, (caller, call, stack_symbol): (Item, usize, usize), nested: Item) {
+        let length = self.done[&caller].0 + self.length(call) + self.done[&nested].0;
+        let (_, caller_state) = self.states[caller.1];
+        let (end, nested_state) = self.states[nested.1];
+        for (symbol, popped, next) in self.first.returns[end].clone() {
+            if popped != stack_symbol {
+                continue;
+            }
+            for letter in self.letters_of[symbol].clone() {
+                let second_next = self
+                    .second
+                    .as_mut()
+                    .map_or(0, |second| second.ret(caller_state, call, nested_state, letter));
+                let next = self.state((next, second_next));
+                let derivation = Derivation::Nested(caller, call, nested, letter);
+                self.push((caller.0, next), length + self.length(letter), derivation);
+            }
+        }
+    }
+
+    fn word(&self, item: Item) -> String {
+        match self.done[&item].1 {
+            Derivation::Start => String::new(),
+            Derivation::Epsilon(previous) => self.word(previous),
+            Derivation::Internal(previous, letter) => self.word(previous) + &self.letters[letter].witness,
+            Derivation::Nested(caller, call, nested, letter) => {
+                self.word(caller)
+                    + &self.letters[call].witness
+                    + &self.word(nested)
+                    + &self.letters[letter].witness
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Recognizer, Sampler};
+
+    #[test]
+    fn equivalent_recognizers_agree_on_samples() {
+        let grammar = "Stmt:\n  Expr=E \";\" -> E\nExpr:\n  \"[0-9]+\" -> \"n\"\n  [\"\\(\" Expr=E \"\\)\"] -> E\n  \
+                       [\"\\[\" Expr=E \"\\]\"] -> E";
+        let before = Recognizer::new(grammar).unwrap();
+        let after = grammar.replace("\"[0-9]+\" -> \"n\"", "N=N -> N\nN:\n  \"[0-9]+\" -> \"n\"");
+        let after = Recognizer::new(&after).unwrap();
+        assert_eq!(before.equivalent(&after).unwrap(), None);
+        let mut sampler = Sampler::new(grammar).unwrap().with_seed(9);
+        for _ in 0..20 {
+            for input in [sampler.accepted().unwrap(), sampler.near_miss().unwrap()] {
+                assert_eq!(before.recognize(&input), after.recognize(&input));
+            }
+        }
+        let changed = Recognizer::new(&grammar.replace("\\[\" Expr=E \"\\]", "\\[\" Expr=E \"\\]+")).unwrap();
+        let counterexample = before.equivalent(&changed).unwrap().unwrap();
+        assert_ne!(before.recognize(&counterexample), changed.recognize(&counterexample));
+    }
+
+    #[test]
+    fn alternatives_that_start_alike_are_refused() {
+        let first = Recognizer::new("A:\n  \"a\" \"c\" -> \"a\"").unwrap();
+        let second = Recognizer::new("A:\n  \"a\" \"b\" -> \"a\"\n  \"a\" \"c\" -> \"a\"").unwrap();
+        assert!(first.recognize("ac").is_some());
+        assert!(second.recognize("ac").is_none());
+        assert!(first.is_subset_of(&second).is_err());
+        assert_eq!(first.equivalent(&second).unwrap(), Some("ab".to_string()));
+        assert_eq!(second.is_subset_of(&first).unwrap(), Some("ab".to_string()));
+    }
+}
@@ -0,0 +1,111 @@
+use std::fmt::Write;
+
+use super::{recognizer_automaton::Action, Recognizer, State};
+
+impl Recognizer {
+    /// Exports the automaton of the recognizer to the DOT language of Graphviz.
+    ///
+    /// Every state is labelled with its number and the nonterminal and rule it is part of. State 0 is the state at
+    /// the end of a nonterminal, which accepts if the stack is empty. The edges are labelled with their regular
+    /// expression, and with the stack symbol that is pushed by a call (`push`), or popped by a return (`pop`).
+    /// Edges that do not consume input are dashed, their regular expression is only a guard.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vpl_parser_generator::Recognizer;
+    ///
+    /// let recognizer = Recognizer::new("A:\n  [\"\\(\" A=A \"\\)\"] -> A\n  \"a\" -> \"a\"").unwrap();
+    /// let dot = recognizer.to_dot();
+    /// assert!(dot.starts_with("digraph"));
+    /// assert!(dot.contains(r#"1 -> 1 [label="\\( / push 2"]"#));
+    /// assert!(dot.contains(r#"0 -> 0 [label="\\) / pop 2"]"#));
+    /// ```
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        self.write_dot(&mut dot).unwrap();
+        dot
+    }
+
+    fn write_dot(&self, dot: &mut String) -> std::fmt::Result {
+        writeln!(dot, "digraph automaton {{")?;
+        writeln!(dot, "    rankdir=LR;")?;
+        writeln!(dot, "    node [shape=circle];")?;
+        writeln!(dot, "    start [shape=point];")?;
+        writeln!(dot, "    start -> 1;")?;
+        let mut states: Vec<State> = self.next_state.keys().copied().collect();
+        states.extend(self.exits.values());
+        states.push(0);
+        states.sort();
+        states.dedup();
+        for state in &states {
+            let shape = if *state == 0 { ", shape=doublecircle" } else { "" };
+            writeln!(
+                dot,
+                "    {state} [label=\"{}\"{shape}];",
+                escape(&self.state_label(*state))
+            )?;
+        }
+        let mut exits: Vec<(&State, &State)> = self.exits.iter().collect();
+        exits.sort();
+        for (point, next) in exits {
+            writeln!(dot, "    0 -> {next} [label=\"pop {point}\", style=dashed];")?;
+        }
+        for state in &states {
+            let Some((regex, actions)) = self.next_state.get(state) else {
+                continue;
+            };
+            for (alternative, action) in regex.alternatives.iter().zip(actions) {
+                let alternative = escape(alternative);
+                match action {
+                    Action::Call(point, next, _, _) => {
+                        writeln!(dot, "    {state} -> {next} [label=\"{alternative} / push {point}\"];")?;
+                    }
+                    Action::Enter(point, next, _) => writeln!(
+                        dot,
+                        "    {state} -> {next} [label=\"{alternative} / push {point}\", style=dashed];"
+                    )?,
+                    Action::Next(next, _) => {
+                        writeln!(dot, "    {state} -> {next} [label=\"{alternative}\"];")?;
+                    }
+                    Action::Skip(next) => {
+                        writeln!(dot, "    {state} -> {next} [label=\"{alternative}\", style=dashed];")?;
+                    }
+                    Action::Return(points) => {
+                        let mut points: Vec<_> = points.iter().collect();
+                        points.sort_by_key(|(point, _)| **point);
+                        for (point, (next, _)) in points {
+                            writeln!(dot, "    {state} -> {next} [label=\"{alternative} / pop {point}\"];")?;
+                        }
+                    }
+                }
+            }
+        }
+        writeln!(dot, "}}")
+    }
+
+    /// Gives the number of a state, with the nonterminal and rule it is part of.
+    fn state_label(&self, state: State) -> String {
+        if state == 0 {
+            return "0\nend".to_string();
+        }
+        if let Some(name) = self.names.get(state - 1) {
+            return format!("{state}\n{name}");
+        }
+        match self.state_to_rule.get(&state) {
+            Some((nonterminal, rule)) => {
+                let name = self.names.get(nonterminal - 1).map_or("?", String::as_str);
+                format!("{state}\n{name} rule {}", rule + 1)
+            }
+            None => state.to_string(),
+        }
+    }
+}
+
+/// Escapes a label for a quoted string of the DOT language, where a backslash also starts an escape.
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,165 @@
+//! Events of a parse, which are given while the input is read instead of building a parse tree.
+
+use std::{collections::VecDeque, ops::Range};
+
+use super::{
+    recognizer_automaton::{ActionType, Run},
+    Recognizer,
+};
+
+/// A step of a parse, in the order of the input.
+///
+/// The nonterminals are numbered like the `identifier` of the nodes of a parse tree, and the rules like their
+/// `rule_nr`. The spans are byte offsets in the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<'t> {
+    /// Starts a nonterminal with the rule it chooses, at the call symbol that enters it, which is an empty span for
+    /// the root and for an identifier.
+    Enter {
+        nonterminal: usize,
+        rule: usize,
+        span: Range<usize>,
+    },
+    /// A named capture group of the nonterminal that was entered last and not exited yet.
+    Leaf {
+        name: String,
+        text: &'t str,
+        span: Range<usize>,
+    },
+    /// Ends the nonterminal that was entered last, after its return symbol.
+    Exit { nonterminal: usize },
+}
+
+/// An iterator of the events of an input, which is made by [`Recognizer::events`].
+///
+/// An event is an error with the byte offset at which the input is rejected, like [`Recognizer::rejected_at`],
+/// after which there are no more events.
+#[derive(Debug)]
+pub struct Events<'r, 't> {
+    /// The recognizer that reads the input, until the input is read or rejected.
+    recognizer: Option<&'r Recognizer>,
+    run: Run,
+    text: &'t str,
+    /// The number of bytes that were read.
+    offset: usize,
+    /// The events of the last transition that were not given yet.
+    queue: VecDeque<Result<Event<'t>, usize>>,
+    /// The nonterminals that were entered and not exited yet.
+    open: Vec<usize>,
+    /// The nonterminal that was entered by the last transition and the span of its call symbol, as the rule is
+    /// chosen by the transition after it.
+    entered: Option<(usize, Range<usize>)>,
+}
+
+impl Recognizer {
+    /// Parses an input text to events, without building a parse tree.
+    ///
+    /// The events are given while the input is read, so only the nonterminals that are entered and not exited are
+    /// kept. Capture groups that do not participate in a match are left out, instead of being empty leaves.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vpl_parser_generator::{Event, Recognizer};
+    ///
+    /// let grammar = "Stmt:\n  Expr=E \";\" -> E\nExpr:\n  \"(?P<n>[0-9]+)\" -> n\n  [\"\\(\" Expr=E \"\\)\"] -> E";
+    /// let recognizer = Recognizer::new(grammar).unwrap();
+    /// let events: Vec<_> = recognizer.events("(4);").collect::<Result<_, _>>().unwrap();
+    /// assert_eq!(
+    ///     events,
+    ///     [
+    ///         Event::Enter { nonterminal: 0, rule: 0, span: 0..0 },
+    ///         Event::Enter { nonterminal: 1, rule: 1, span: 0..0 },
+    ///         Event::Enter { nonterminal: 1, rule: 0, span: 0..1 },
+    ///         Event::Leaf { name: "n".to_string(), text: "4", span: 1..2 },
+    ///         Event::Exit { nonterminal: 1 },
+    ///         Event::Exit { nonterminal: 1 },
+    ///         Event::Exit { nonterminal: 0 },
+    ///     ]
+    /// );
+    /// let leaves = recognizer.events("((1));").filter(|event| matches!(event, Ok(Event::Leaf { .. }))).count();
+    /// assert_eq!(leaves, 1);
+    /// assert_eq!(recognizer.events("((1]);").last(), Some(Err(3)));
+    /// ```
+    pub fn events<'r, 't>(&'r self, text: &'t str) -> Events<'r, 't> {
+        Events {
+            recognizer: Some(self),
+            run: Run::new(1),
+            text,
+            offset: 0,
+            queue: VecDeque::new(),
+            open: vec![0],
+            entered: Some((0, 0..0)),
+        }
+    }
+}
+
+impl<'t> Events<'_, 't> {
+    /// Takes a transition, and adds its events to the queue.
+    fn step(&mut self) {
+        let Some(recognizer) = self.recognizer else {
+            return;
+        };
+        let text = &self.text[self.offset..];
+        let previous_state = self.run.state;
+        let Some((rest, spans, action, matches)) = recognizer.next_state(&mut self.run, text) else {
+            if self.run.accepting() && text.is_empty() {
+                let nonterminal = self.open.pop().unwrap_or_default();
+                self.queue.push_back(Ok(Event::Exit { nonterminal }));
+            } else {
+                self.queue.push_back(Err(self.offset));
+            }
+            self.recognizer = None;
+            return;
+        };
+        if previous_state > 0 && previous_state <= recognizer.names.len() {
+            if let Some((nonterminal, span)) = self.entered.take() {
+                self.queue.push_back(Ok(Event::Enter { nonterminal, rule: matches, span }));
+            }
+        }
+        let names = recognizer
+            .next_state
+            .get(&previous_state)
+            .map_or(&[][..], |(regex, _)| regex.capture_names(matches));
+        let leaves = spans.into_iter().zip(names).filter_map(|(span, name)| {
+            let span = span?;
+            let text = &self.text[self.offset + span.start..self.offset + span.end];
+            let span = self.offset + span.start..self.offset + span.end;
+            Some(Ok(Event::Leaf { name: name.clone(), text, span }))
+        });
+        let read = text.len() - rest.len();
+        match action {
+            ActionType::Call(identifier, _, _) => {
+                self.queue.extend(leaves);
+                self.entered = Some((identifier, self.offset..self.offset + read));
+                self.open.push(identifier);
+            }
+            ActionType::Enter(identifier, _) => {
+                self.entered = Some((identifier, self.offset..self.offset));
+                self.open.push(identifier);
+            }
+            ActionType::Next(_) => self.queue.extend(leaves),
+            ActionType::Skip() => {}
+            ActionType::Return(_) | ActionType::Exit() => {
+                let nonterminal = self.open.pop().unwrap_or_default();
+                self.queue.push_back(Ok(Event::Exit { nonterminal }));
+                self.queue.extend(leaves);
+            }
+        }
+        self.offset += read;
+    }
+}
+
+impl<'t> Iterator for Events<'_, 't> {
+    type Item = Result<Event<'t>, usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.queue.pop_front() {
+                return Some(event);
+            }
+            self.recognizer.as_ref()?;
+            self.step();
+        }
+    }
+}
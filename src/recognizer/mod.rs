@@ -1,15 +1,32 @@
 mod builder;
+mod coverage;
+mod decision;
+mod dot;
+mod events;
 mod recognizer_automaton;
+mod parallel;
 mod parse_tree;
+mod stream;
+mod words;
 
 use std::collections::HashMap;
 
 use crate::regex::RegexParser;
 
-use self::recognizer_automaton::Action;
+pub(crate) use self::recognizer_automaton::Action;
 
 pub(crate) type State = usize;
 pub(crate) type NextMap = HashMap<State, (RegexParser, Vec<Action>)>;
 
+/// Position of a child in a parse tree node. Repeated slots collect a list of children.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Slot {
+    pub(crate) index: usize,
+    pub(crate) repeated: bool,
+}
+
+pub use coverage::Coverage;
+pub use events::{Event, Events};
 pub use recognizer_automaton::Recognizer;
+pub use stream::RecognizerStream;
 pub use parse_tree::{Child, Node, ParseTree, Tree};
\ No newline at end of file
@@ -0,0 +1,356 @@
+//! Recognition of an input in chunks on multiple threads.
+//!
+//! The run of a visibly pushdown automaton over a chunk of the input only depends on the state at its start and the
+//! return points it pops from the stack below it. A chunk is therefore summarized on its own, on a thread of a rayon
+//! thread pool, by running it with an empty stack from every state that a symbol leads to, until the end of the
+//! chunk, until it needs a return point from below, or until it is rejected. A run continues after a return point
+//! from below for every return point it can pop, and runs that reach the same state at the same position with an
+//! empty stack share their summary. The summaries of the chunks are then combined from the start of the input, with
+//! the actual stack.
+//!
+//! A transition reads the rest of the whole input and not only the rest of its chunk, so a symbol that continues in
+//! the next chunk is read like in [`Recognizer::recognize`], and the next chunk then continues after it.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex, OnceLock, PoisonError},
+};
+
+use rayon::{prelude::*, ThreadPool, ThreadPoolBuilder};
+
+use super::{recognizer_automaton::Action, NextMap, Recognizer, State};
+
+/// The thread pool of every number of threads that was asked for, which is reused by later calls.
+static POOLS: OnceLock<Mutex<HashMap<usize, Arc<ThreadPool>>>> = OnceLock::new();
+
+/// How a run from a state at a position with an empty stack ends.
+#[derive(Debug, Clone)]
+enum Summary {
+    /// Reaches the end of the chunk in the state at the position, with the return points it pushed and did not pop.
+    End(State, usize, Vec<State>),
+    /// Needs the return point below its stack, in the end state at the position.
+    Pop(usize),
+    /// Has no transition at the position.
+    Rejected,
+}
+
+/// A transition of the automaton.
+enum Step {
+    Push(State, State),
+    Pop(State),
+    Next(State),
+}
+
+/// The summaries of the runs in a chunk of the input.
+struct Chunk {
+    /// The position at which the runs stop, which is `None` for the last chunk.
+    end: Option<usize>,
+    summaries: Vec<Summary>,
+    /// The summary of every state and position with an empty stack that a run passed.
+    known: HashMap<(State, usize), usize>,
+}
+
+/// The automaton of a recognizer with an input, which is shared by the threads of the pool.
+struct Automaton<'a> {
+    next_state: &'a NextMap,
+    exits: &'a HashMap<State, State>,
+    text: &'a str,
+}
+
+impl Recognizer {
+    /// Recognizes an input text like [`Recognizer::recognize`], by splitting it in up to the given number of chunks
+    /// that are read on the given number of threads.
+    ///
+    /// The threads are a rayon thread pool that is kept for every number of threads, so later calls with the same
+    /// number reuse them. The chunks are split after whitespace where possible, as a chunk that starts within a
+    /// symbol is summarized for nothing and read again once the state before it is known.
+    ///
+    /// Every chunk but the first is read once for every state that a call, return or internal symbol leads to, so
+    /// the work is up to that number of states times the length of the input, spread over the threads, and only pays
+    /// off for grammars with few states on a machine with enough cores. A recognizer of a grammar with many states
+    /// is usually faster with [`Recognizer::recognize`].
+    ///
+    /// Coverage is not recorded, even if the recognizer is recording it, as a summary is shared by every run that
+    /// passes it and most summaries are not used by the run that is combined from them. Use
+    /// [`Recognizer::recognize`] for inputs whose coverage should be recorded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vpl_parser_generator::Recognizer;
+    ///
+    /// let grammar = "List:\n  [\"\\[ ?\" Items=I \"\\] ?\"] -> I\nItems:\n  Item=I* -> I\nItem:\n  \"[0-9]+ ?\" -> \"n\"\n  List=L -> L";
+    /// let recognizer = Recognizer::new(grammar).unwrap();
+    /// let text = "[1 [22 333 [4] ] 55 [[666 7] 8] 99 ]";
+    /// assert_eq!(recognizer.recognize_parallel(text, 4), Some(()));
+    /// assert_eq!(recognizer.recognize_parallel(&text[..text.len() - 1], 4), None);
+    /// assert_eq!(recognizer.recognize_parallel("[1 [22 333 [4]] 55 ] 8]", 3), None);
+    ///
+    /// let mut recognizer = recognizer;
+    /// recognizer.record_coverage();
+    /// recognizer.recognize_parallel(text, 4);
+    /// assert_eq!(recognizer.coverage().unwrap().unused_rules().len(), 4);
+    /// ```
+    pub fn recognize_parallel(&self, text: &str, threads: usize) -> Option<()> {
+        let threads = threads.max(1);
+        // Without a pool of its own, for example when no more threads can be started, the global pool is used.
+        match pool(threads) {
+            Some(pool) => pool.install(|| self.recognize_chunks(text, threads)),
+            None => self.recognize_chunks(text, threads),
+        }
+    }
+
+    /// Recognizes an input text in up to the given number of chunks, which are summarized on the current thread pool.
+    fn recognize_chunks(&self, text: &str, chunks: usize) -> Option<()> {
+        let automaton = Automaton {
+            next_state: &self.next_state,
+            exits: &self.exits,
+            text,
+        };
+        let starts = split(text, chunks);
+        let entries = automaton.entries();
+        let mut chunks: Vec<Chunk> = starts
+            .par_iter()
+            .enumerate()
+            .map(|(index, start)| {
+                let end = starts.get(index + 1).copied();
+                match index {
+                    0 => automaton.summarize(*start, end, [1]),
+                    _ => automaton.summarize(*start, end, entries.iter().copied()),
+                }
+            })
+            .collect();
+        let mut state = 1;
+        let mut position = 0;
+        let mut stack = Vec::new();
+        for chunk in &mut chunks {
+            loop {
+                let index = match chunk.known.get(&(state, position)) {
+                    Some(index) => *index,
+                    None => automaton.run(chunk, state, position),
+                };
+                match &chunk.summaries[index] {
+                    Summary::End(next, after, pushed) => {
+                        stack.extend_from_slice(pushed);
+                        (state, position) = (*next, *after);
+                        break;
+                    }
+                    Summary::Pop(at) => match stack.pop() {
+                        Some(point) => (state, position) = automaton.pop(point, *at)?,
+                        None => return (*at == text.len()).then_some(()),
+                    },
+                    Summary::Rejected => return None,
+                }
+            }
+        }
+        // The last chunk has no end, so its runs do not end there.
+        None
+    }
+}
+
+impl Automaton<'_> {
+    /// Takes a transition from the state at the position, with the return point on top of the stack, like
+    /// [`Recognizer::next_state`], and gives it with the position after it.
+    fn step(&self, state: State, top: Option<State>, position: usize) -> Option<(Step, usize)> {
+        if state == 0 {
+            if let Some(next) = top.and_then(|point| self.exits.get(&point)) {
+                return Some((Step::Pop(*next), position));
+            }
+        }
+        let (regex, actions) = self.next_state.get(&state)?;
+        let (matches, rest, _) = regex.parse(&self.text[position..])?;
+        let after = self.text.len() - rest.len();
+        Some(match actions.get(matches)? {
+            Action::Call(point, next, _, _) => (Step::Push(*point, *next), after),
+            Action::Enter(point, next, _) => (Step::Push(*point, *next), position),
+            Action::Next(next, _) => (Step::Next(*next), after),
+            Action::Skip(next) => (Step::Next(*next), position),
+            Action::Return(map) => (Step::Pop(map.get(&top?)?.0), after),
+        })
+    }
+
+    /// Pops the return point in the end state at the position, and gives the state and position after it.
+    fn pop(&self, point: State, position: usize) -> Option<(State, usize)> {
+        match self.step(0, Some(point), position)? {
+            (Step::Pop(next), after) => Some((next, after)),
+            _ => None,
+        }
+    }
+
+    /// Gives the states that a call, return or internal symbol leads to, which are the states a run can be in when
+    /// it reaches the start of a chunk. A run that reaches it in the middle of a symbol is run again when combining.
+    fn entries(&self) -> HashSet<State> {
+        let mut entries = HashSet::new();
+        for (_, actions) in self.next_state.values() {
+            for action in actions {
+                match action {
+                    Action::Call(_, next, _, _) | Action::Next(next, _) => {
+                        entries.insert(*next);
+                    }
+                    Action::Return(map) => entries.extend(map.values().map(|(next, _)| *next)),
+                    Action::Enter(..) | Action::Skip(_) => {}
+                }
+            }
+        }
+        entries
+    }
+
+    /// Gives the return points that can be popped in the end state.
+    fn points(&self) -> HashSet<State> {
+        let mut points: HashSet<State> = self.exits.keys().copied().collect();
+        if let Some((_, actions)) = self.next_state.get(&0) {
+            for action in actions {
+                if let Action::Return(map) = action {
+                    points.extend(map.keys());
+                }
+            }
+        }
+        points
+    }
+
+    /// Summarizes the runs of a chunk from the given states at its start, and the runs after the return points they
+    /// can pop from below.
+    fn summarize(&self, start: usize, end: Option<usize>, states: impl IntoIterator<Item = State>) -> Chunk {
+        let mut chunk = Chunk {
+            end,
+            summaries: Vec::new(),
+            known: HashMap::new(),
+        };
+        let points = self.points();
+        let mut popped = HashSet::new();
+        let mut queue: Vec<(State, usize)> = states.into_iter().map(|state| (state, start)).collect();
+        while let Some((state, position)) = queue.pop() {
+            if chunk.known.contains_key(&(state, position)) {
+                continue;
+            }
+            let index = self.run(&mut chunk, state, position);
+            if let Summary::Pop(at) = chunk.summaries[index] {
+                if popped.insert(at) {
+                    queue.extend(points.iter().filter_map(|point| self.pop(*point, at)));
+                }
+            }
+        }
+        chunk
+    }
+
+    /// Runs from the state at the position with an empty stack, and gives the index of its summary, which is also the
+    /// summary of the states and positions with an empty stack that it passes.
+    fn run(&self, chunk: &mut Chunk, mut state: State, mut position: usize) -> usize {
+        let mut stack = Vec::new();
+        let mut passed = Vec::new();
+        // The summary of the run, or the index of the known summary it continues with.
+        let summary = loop {
+            if stack.is_empty() {
+                if let Some(index) = chunk.known.get(&(state, position)) {
+                    break Err(*index);
+                }
+                passed.push((state, position));
+            }
+            if chunk.end.is_some_and(|end| position >= end) {
+                break Ok(Summary::End(state, position, stack));
+            }
+            if state == 0 && stack.is_empty() {
+                break Ok(Summary::Pop(position));
+            }
+            let Some((step, after)) = self.step(state, stack.last().copied(), position) else {
+                break Ok(Summary::Rejected);
+            };
+            state = match step {
+                Step::Push(point, next) => {
+                    stack.push(point);
+                    next
+                }
+                Step::Pop(next) => {
+                    stack.pop();
+                    next
+                }
+                Step::Next(next) => next,
+            };
+            position = after;
+        };
+        let index = match summary {
+            Ok(summary) => {
+                chunk.summaries.push(summary);
+                chunk.summaries.len() - 1
+            }
+            Err(index) => index,
+        };
+        for configuration in passed {
+            chunk.known.insert(configuration, index);
+        }
+        index
+    }
+}
+
+/// Gives the thread pool with the number of threads, which is built the first time it is asked for.
+fn pool(threads: usize) -> Option<Arc<ThreadPool>> {
+    let mut pools = POOLS.get_or_init(Mutex::default).lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(pool) = pools.get(&threads) {
+        return Some(Arc::clone(pool));
+    }
+    let pool = Arc::new(ThreadPoolBuilder::new().num_threads(threads).build().ok()?);
+    pools.insert(threads, Arc::clone(&pool));
+    Some(pool)
+}
+
+/// Splits a text in up to the given number of chunks of about the same length, and gives their starts.
+///
+/// A chunk starts after whitespace where there is whitespace in the first half of it, as that usually ends a symbol.
+fn split(text: &str, chunks: usize) -> Vec<usize> {
+    let size = text.len() / chunks;
+    let mut starts = vec![0];
+    for chunk in 1..chunks {
+        let Some(middle) = (chunk * size..text.len()).find(|index| text.is_char_boundary(*index)) else {
+            break;
+        };
+        let start = text[middle..]
+            .char_indices()
+            .take_while(|(offset, _)| *offset < size / 2)
+            .find(|(_, character)| character.is_whitespace())
+            .map_or(middle, |(offset, character)| middle + offset + character.len_utf8());
+        if start > *starts.last().unwrap() && start < text.len() {
+            starts.push(start);
+        }
+    }
+    starts
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Recognizer, Sampler};
+
+    /// The answer is that of [`Recognizer::recognize`] for any number of chunks, also when they start within a symbol.
+    #[test]
+    fn chunks_agree_with_recognize() {
+        let grammar = "Stmt:\n  Expr=E \";\" -> E\nExpr:\n  \"[0-9]+\" -> \"n\"\n  [\"\\(\" Expr=E \"\\)\"] -> E\n  [\"\\[\" Expr=E \"\\]\"] -> E";
+        let recognizer = Recognizer::new(grammar).unwrap();
+        let mut sampler = Sampler::new(grammar).unwrap().with_seed(5);
+        for _ in 0..20 {
+            let accepted = sampler.accepted().unwrap();
+            let near_miss = sampler.near_miss().unwrap();
+            for input in [&accepted, &near_miss, &accepted[..accepted.len() / 2]] {
+                for chunks in 1..=input.len() {
+                    assert_eq!(recognizer.recognize_chunks(input, chunks), recognizer.recognize(input));
+                }
+            }
+        }
+        let grammar = "List:\n  [\"\\[ ?\" Items=I \"\\] ?\"] -> I\nItems:\n  Item=I* -> I\nItem:\n  \"[0-9]+ ?\" -> \"n\"\n  List=L -> L";
+        let recognizer = Recognizer::new(grammar).unwrap();
+        let text = "[1 [22 333 [4] ] 55 [[666 7] 8] 99 ]";
+        for input in [text, &text[..text.len() - 1], "[1 [22 333 [4]] 55 ] 8]"] {
+            for chunks in 1..=input.len() {
+                assert_eq!(recognizer.recognize_chunks(input, chunks), recognizer.recognize(input));
+            }
+        }
+    }
+
+    #[test]
+    fn threads_are_reused() {
+        let recognizer = Recognizer::new("A:\n  [\"\\(\" A=A \"\\)\"]* \"a\" -> A").unwrap();
+        for threads in [1, 3, 3, 0] {
+            assert_eq!(recognizer.recognize_parallel("(a)(a)a", threads), Some(()));
+        }
+        assert_eq!(super::pool(3).unwrap().current_num_threads(), 3);
+        assert!(std::sync::Arc::ptr_eq(&super::pool(3).unwrap(), &super::pool(3).unwrap()));
+    }
+}
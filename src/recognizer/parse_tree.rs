@@ -4,6 +4,8 @@ use std::{rc::{Weak, Rc}, cell::RefCell};
 pub enum Child {
     Node(Rc<RefCell<Node>>),
     Leaf(Leaf),
+    /// The children of a repeated identifier or capture group, in the order they were matched.
+    List(Vec<Child>),
 }
 
 pub type Leaf = String;
@@ -18,14 +20,51 @@ pub struct Node {
 
 pub trait ParseTree {
     fn new(identifier: usize, rule_nr: usize) -> Self;
-    fn add_node(&self, identifier: usize, rule_nr: usize) -> Option<Tree>;
-    fn add_leaf(&self, value: &str);
+    fn add_node(&self, index: usize, repeated: bool, identifier: usize, rule_nr: usize) -> Option<Tree>;
+    fn add_leaf(&self, index: usize, repeated: bool, value: &str);
     fn parent(&self) -> Option<Tree>;
     fn child(&self, identifier: usize) -> Option<Child>;
 }
 
 pub type Tree = Rc<RefCell<Node>>;
 
+impl Node {
+    /// Places a child at the index, or adds it to the list at the index if it is repeated.
+    ///
+    /// Children before the index that are not set yet are empty leaves.
+    fn insert(&mut self, index: usize, repeated: bool, child: Child) {
+        if self.children.len() <= index {
+            self.children.resize(index + 1, Child::Leaf(Leaf::new()));
+        }
+        if !repeated {
+            self.children[index] = child;
+        } else if let Child::List(list) = &mut self.children[index] {
+            list.push(child);
+        } else {
+            self.children[index] = Child::List(vec![child]);
+        }
+    }
+}
+
+/// Drops the children with an explicit stack, as a tree of a right recursive word can be deeper than the call stack.
+impl Drop for Node {
+    fn drop(&mut self) {
+        let mut children = std::mem::take(&mut self.children);
+        while let Some(child) = children.pop() {
+            match child {
+                Child::Node(node) => {
+                    // A node that is still shared is dropped by its last owner.
+                    if let Ok(node) = Rc::try_unwrap(node) {
+                        children.append(&mut node.into_inner().children);
+                    }
+                }
+                Child::List(mut list) => children.append(&mut list),
+                Child::Leaf(_) => {}
+            }
+        }
+    }
+}
+
 impl ParseTree for Tree {
     fn new(identifier: usize, rule_nr: usize) -> Self {
         let node = Node {
@@ -38,6 +77,8 @@ impl ParseTree for Tree {
     }
     fn add_node(
         &self,
+        index: usize,
+        repeated: bool,
         identifier: usize,
         rule_nr: usize,
     ) -> Option<Rc<RefCell<Node>>> {
@@ -50,22 +91,22 @@ impl ParseTree for Tree {
         };
         new_node.parent = Some(Rc::downgrade(self));
         let new_ref = Rc::new(RefCell::new(new_node));
-        node.children.push(Child::Node(new_ref.clone()));
+        node.insert(index, repeated, Child::Node(new_ref.clone()));
         Some(new_ref)
     }
-    fn add_leaf(&self, value: &str) {
+    fn add_leaf(&self, index: usize, repeated: bool, value: &str) {
         let mut node = self.as_ref().borrow_mut();
         let new_node = value.to_string();
-        node.children.push(Child::Leaf(new_node));
+        node.insert(index, repeated, Child::Leaf(new_node));
     }
-    
+
     fn parent(&self) -> Option<Rc<RefCell<Node>>> {
         let node = self.as_ref().borrow();
         node.parent.as_ref().and_then(|p| p.upgrade())
     }
-    
+
     fn child(&self, identifier: usize) -> Option<Child> {
         let mut node = self.as_ref().borrow_mut();
         node.children.get_mut(identifier).cloned()
     }
-}
\ No newline at end of file
+}
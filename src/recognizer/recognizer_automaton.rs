@@ -1,39 +1,92 @@
-use std::{
-    cell::RefCell,
-    collections::HashMap,
-    error::Error,
-    rc::{Rc},
-};
+use std::{collections::HashMap, error::Error, sync::Arc};
 
-use crate::{ir::*};
+use crate::{ir::*, regex::Spans};
 
-use super::{builder::RecognizerBuilder, NextMap, Tree, Node, ParseTree};
+use super::{builder::RecognizerBuilder, coverage::Recording, NextMap, Slot, Tree, ParseTree};
 
 type State = usize;
 type Identifier = usize;
 #[derive(Debug, Clone)]
 pub(crate) enum Action {
-    Call(State, State, Vec<(Identifier, usize)>),
-    Next(State, Vec<(Identifier, usize)>),
-    Return(HashMap<State, State>),
+    /// Consumes a call symbol, pushes the return point and enters the nonterminal.
+    Call(State, Identifier, Slot, Slot),
+    /// Pushes the return point and enters the nonterminal, without consuming input.
+    Enter(State, Identifier, Slot),
+    Next(State, Slot),
+    /// Continues in the state, without consuming input.
+    Skip(State),
+    /// Consumes a return symbol, and continues in the state of the popped return point.
+    Return(HashMap<State, (State, Slot)>),
 }
 
 #[derive(Debug, Clone)]
 pub(crate) enum ActionType {
-    Call(Vec<(usize, usize)>),
-    Return(),
-    Next(Vec<(usize, usize)>),
+    Call(usize, Slot, Slot),
+    Enter(usize, Slot),
+    Next(Slot),
+    Skip(),
+    Return(Slot),
+    /// Leaves a nonterminal entered by an identifier.
+    Exit(),
 }
 
 /// Struct that can recognize and parse an input text to a specific parse tree.
 /// 
-/// This struct can recognize or parse a subset of VPLs in linear time.
-#[derive(Debug)]
+/// This struct can recognize or parse a subset of VPLs in linear time. It only holds the automaton, as the state of
+/// a run is kept by the run, so one recognizer can be shared by threads, for example in an `Arc`.
+///
+/// # Examples
+///
+/// ```
+/// use std::{sync::Arc, thread};
+/// use vpl_parser_generator::Recognizer;
+///
+/// let recognizer = Arc::new(Recognizer::new("A:\n  [\"\\(\" A=A \"\\)\"] -> A\n  \"a\" -> \"a\"").unwrap());
+/// let handles: Vec<_> = ["(a)", "((a))", "(a"]
+///     .into_iter()
+///     .map(|input| {
+///         let recognizer = Arc::clone(&recognizer);
+///         thread::spawn(move || recognizer.recognize(input).is_some())
+///     })
+///     .collect();
+/// let accepted: Vec<bool> = handles.into_iter().map(|handle| handle.join().unwrap()).collect();
+/// assert_eq!(accepted, [true, true, false]);
+/// ```
+#[derive(Debug, Clone)]
 pub struct Recognizer {
-    pub(crate) stack: RefCell<Vec<State>>,
+    // The automaton is shared by the clones of a recognizer, which only differ in their recording.
+    pub(crate) next_state: Arc<NextMap>,
+    /// Maps the return points of identifiers to the state after the identifier.
+    pub(crate) exits: Arc<HashMap<State, State>>,
+    /// The names of the nonterminals, whose starting states are 1 up to the number of nonterminals.
+    pub(crate) names: Arc<Vec<String>>,
+    /// Maps the other states to the nonterminal and the index of the rule they are part of.
+    pub(crate) state_to_rule: Arc<HashMap<State, (usize, usize)>>,
+    /// The uses of the rules and states, if they are recorded.
+    pub(crate) coverage: Option<Box<Recording>>,
+}
+
+/// The state of a run of a recognizer over an input.
+#[derive(Debug, Clone)]
+pub(crate) struct Run {
     pub(crate) state: State,
-    pub(crate) next_state: NextMap,
-    pub(crate) nonterminals_length: usize,
+    /// The return points of the nested blocks and identifiers that were entered and not left.
+    pub(crate) stack: Vec<State>,
+}
+
+impl Run {
+    /// Starts a run in the starting state of a nonterminal.
+    pub(crate) fn new(start: State) -> Self {
+        Run {
+            state: start,
+            stack: Vec::new(),
+        }
+    }
+
+    /// Checks if the run is at the end of its nonterminal, and not in a nested block or identifier.
+    pub(crate) fn accepting(&self) -> bool {
+        self.state == 0 && self.stack.is_empty()
+    }
 }
 
 
@@ -64,47 +117,70 @@ impl Recognizer {
     /// assert!(incorrect_recognizer.is_err());
     /// ```
     pub fn new(string: &str) -> Result<Self, Box<dyn Error + '_>> {
-        let (rest_string, grammar) = crate::ast::Grammar::parse(string)?;
-        if !rest_string.is_empty() {
-            return Err("Error, entire file not parsed".into());
-        }
+        let grammar = crate::ast::Grammar::parse_complete(string)?;
         let elaborated = crate::elaborator::elaborate(grammar)?;
         Ok(crate::recognizer::Recognizer::from(&elaborated))
     }
 
-    pub(crate) fn push(&self, state: State) {
-        self.stack.borrow_mut().push(state);
-    }
-
-    pub(crate) fn pop(&self) -> Option<State> {
-        self.stack.borrow_mut().pop()
+    /// Gives the names of the nonterminals, in the order of the `identifier` of the nodes of a parse tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vpl_parser_generator::Recognizer;
+    ///
+    /// let recognizer = Recognizer::new("A:\n  B=B -> B\nB:\n  \"b\" -> \"b\"").unwrap();
+    /// let (tree, _) = recognizer.parse("b").unwrap();
+    /// assert_eq!(recognizer.nonterminals()[tree.borrow().identifier], "A");
+    /// ```
+    pub fn nonterminals(&self) -> &[String] {
+        &self.names
     }
 
+    /// Takes a transition of a run on the start of the text, and gives the rest of the text, the spans of the captures
+    /// in the text, the action and the alternative that matched.
     pub(crate) fn next_state<'a>(
-        &mut self,
+        &self,
+        run: &mut Run,
         text: &'a str,
-    ) -> Option<(&'a str, HashMap<String, String>, ActionType, usize)> {
-        let (regex, actions) = self.next_state.get(&self.state)?;
+    ) -> Option<(&'a str, Spans, ActionType, usize)> {
+        if run.state == 0 {
+            if let Some(next) = run.stack.last().and_then(|point| self.exits.get(point)) {
+                run.state = *next;
+                run.stack.pop();
+                return Some((text, Vec::new(), ActionType::Exit(), 0));
+            }
+        }
+        let (regex, actions) = self.next_state.get(&run.state)?;
         let (matches, rest_text, captures) = regex.parse(text)?;
         let action = actions.get(matches)?;
-        match action {
-            Action::Call(state, next, _) => {
-                self.push(*state);
-                self.state = *next;
+        if let Some(coverage) = &self.coverage {
+            coverage.record(run.state, matches);
+        }
+        let (rest_text, id) = match action {
+            Action::Call(point, next, captures_slot, node_slot) => {
+                run.stack.push(*point);
+                run.state = *next;
+                (rest_text, ActionType::Call(next - 1, *captures_slot, *node_slot))
             }
-            Action::Next(next, _) => {
-                self.state = *next;
+            Action::Enter(point, next, slot) => {
+                run.stack.push(*point);
+                run.state = *next;
+                (text, ActionType::Enter(next - 1, *slot))
+            }
+            Action::Next(next, slot) => {
+                run.state = *next;
+                (rest_text, ActionType::Next(*slot))
+            }
+            Action::Skip(next) => {
+                run.state = *next;
+                (text, ActionType::Skip())
             }
             Action::Return(map) => {
-                let orig_state = &self.pop()?;
-                let next = map.get(orig_state)?;
-                self.state = *next;
+                let (next, slot) = map.get(&run.stack.pop()?)?;
+                run.state = *next;
+                (rest_text, ActionType::Return(*slot))
             }
-        }
-        let id = match action {
-            Action::Call(_, _, id) => ActionType::Call(id.clone()),
-            Action::Next(_, id) => ActionType::Next(id.clone()),
-            Action::Return(_) => ActionType::Return(),
         };
         Some((rest_text, captures, id, matches))
     }
@@ -120,109 +196,120 @@ impl Recognizer {
     /// use vpl_parser_generator::Recognizer;
     ///
     /// let string = fs::read_to_string("examples/a-lang.vpa").unwrap();
-    /// let mut recognizer = Recognizer::new(&string).unwrap();
+    /// let recognizer = Recognizer::new(&string).unwrap();
     /// assert!(recognizer.parse("aaaaa").is_some());
     /// assert!(recognizer.parse("b").is_none());
     /// ```
-    pub fn parse(&mut self, text: &str) -> Option<(Tree, usize)> {
+    pub fn parse(&self, text: &str) -> Option<(Tree, usize)> {
+        self.parse_nonterminal(1, text)
+    }
+
+    /// Parses an input text as the nonterminal with the given starting state, like [`Recognizer::parse`].
+    pub(crate) fn parse_nonterminal(&self, start: State, text: &str) -> Option<(Tree, usize)> {
+        let mut run = Run::new(start);
         let mut rest_text = text;
         let mut size = 0;
-        let root = Rc::new(RefCell::new(Node {
-            children: Vec::new(),
-            identifier: 0,
-            rule_nr: 0,
-            parent: None,
-        }));
+        let root = <Tree as ParseTree>::new(start - 1, 0);
         let mut current_tree = root.clone();
-        let mut call_stack = Vec::new();
-        let mut previous_state = 1;
-        while let Some((text, captures, action, matches)) = self.next_state(rest_text) {
+        loop {
+            let previous_state = run.state;
+            let Some((text, spans, action, matches)) = self.next_state(&mut run, rest_text) else {
+                break;
+            };
+            let captures: Vec<&str> = spans.into_iter().map(|span| span.map_or("", |span| &rest_text[span])).collect();
             rest_text = text;
-            if previous_state <= self.nonterminals_length && previous_state > 0 {
+            if previous_state <= self.names.len() && previous_state > 0 {
                 current_tree.borrow_mut().rule_nr = matches;
             }
-            match action {
-                ActionType::Call(i) => {
-                    i.iter()
-                        .take(i.iter().len() - 1)
-                        .for_each(|(identifier, rule_nr)| {
-                            call_stack.push(Call::Identifier);
-                            current_tree =
-                                current_tree.add_node(*identifier, *rule_nr).unwrap();
-                        });
-                    call_stack.push(Call::Nested);
-                    current_tree = current_tree.add_node(
-                        i.last().unwrap().0,
-                        i.last().unwrap().1,
-                    )
-                    .unwrap();
+            let captures_slot = match action {
+                ActionType::Call(identifier, captures_slot, node_slot) => {
+                    add_leaves(&current_tree, captures_slot, &captures);
+                    current_tree = current_tree
+                        .add_node(node_slot.index, node_slot.repeated, identifier, 0)
+                        .unwrap();
+                    None
                 }
-                ActionType::Next(i) => {
-                    i.iter().rev().for_each(|(identifier, rule_nr)| {
-                        call_stack.push(Call::Identifier);
-                        current_tree =
-                            current_tree.add_node(*identifier, *rule_nr).unwrap();
-                    });
+                ActionType::Enter(identifier, slot) => {
+                    current_tree = current_tree
+                        .add_node(slot.index, slot.repeated, identifier, 0)
+                        .unwrap();
+                    None
                 }
-                ActionType::Return() => {
-                    call_stack.pop();
+                ActionType::Next(slot) => Some(slot),
+                ActionType::Skip() => None,
+                ActionType::Return(slot) => {
                     current_tree = current_tree.parent()?;
+                    Some(slot)
                 }
-            }
-            captures.into_iter().for_each(|(_k, v)| {
-                current_tree.add_leaf(&v);
-                size += v.len();
-            });
-            if self.state == 0 {
-                while !call_stack.is_empty() && *call_stack.last()? == Call::Identifier {
-                    call_stack.pop();
+                ActionType::Exit() => {
                     current_tree = current_tree.parent()?;
+                    None
                 }
+            };
+            if let Some(slot) = captures_slot {
+                add_leaves(&current_tree, slot, &captures);
             }
-
-            if rest_text.is_empty() {
-                break;
-            }
-            previous_state = self.state;
+            size += captures.iter().map(|c| c.len()).sum::<usize>();
         }
-        let result = self.accepting_state() && rest_text.is_empty();
-        self.reset();
-        if result {
+        if run.accepting() && rest_text.is_empty() {
             Some((root, size))
         } else {
             None
         }
     }
 
-    pub fn recognize(&mut self, text: &str) -> Option<()> {
+    /// Recognizes an input text, without building a parse tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vpl_parser_generator::Recognizer;
+    ///
+    /// let grammar = "Stmt:\n  Expr=E \";\" -> E\nExpr:\n  \"[0-9]+\" -> \"n\"\n  [\"\\(\" Expr=E \"\\)\"] -> E";
+    /// let recognizer = Recognizer::new(grammar).unwrap();
+    /// assert!(recognizer.recognize("((42));").is_some());
+    /// assert!(recognizer.recognize("((42);").is_none());
+    /// ```
+    pub fn recognize(&self, text: &str) -> Option<()> {
+        match self.rejected_at(text) {
+            Some(_) => None,
+            None => Some(()),
+        }
+    }
+
+    /// Gives the byte offset at which an input text is rejected, or `None` if it is recognized.
+    ///
+    /// This is the offset of the first input that has no transition in the automaton, or the length of the text if
+    /// it ends in a state that does not accept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vpl_parser_generator::Recognizer;
+    ///
+    /// let grammar = "Stmt:\n  Expr=E \";\" -> E\nExpr:\n  \"[0-9]+\" -> \"n\"\n  [\"\\(\" Expr=E \"\\)\"] -> E";
+    /// let recognizer = Recognizer::new(grammar).unwrap();
+    /// assert_eq!(recognizer.rejected_at("((42));"), None);
+    /// assert_eq!(recognizer.rejected_at("((42]);"), Some(4));
+    /// assert_eq!(recognizer.rejected_at("((42)"), Some(5));
+    /// ```
+    pub fn rejected_at(&self, text: &str) -> Option<usize> {
+        let mut run = Run::new(1);
         let mut rest_text = text;
-        while let Some((text, _, _, _)) = self.next_state(rest_text) {
+        while let Some((text, _, _, _)) = self.next_state(&mut run, rest_text) {
             rest_text = text;
-            if rest_text.is_empty() {
-                break;
-            }
         }
-        let result = self.accepting_state() && rest_text.is_empty();
-        self.reset();
-        if result {
-            Some(())
-        } else {
+        if run.accepting() && rest_text.is_empty() {
             None
+        } else {
+            Some(text.len() - rest_text.len())
         }
     }
-
-    pub(crate) fn reset(&mut self) {
-        self.stack.borrow_mut().clear();
-        self.state = 1;
-    }
-
-    pub(crate) fn accepting_state(&self) -> bool {
-        self.state == 0 && self.stack.borrow().is_empty()
-    }
 }
 
-#[derive(Debug, PartialEq)]
-enum Call {
-    Nested,
-    Identifier,
+/// Adds the captures of a match as leaves, starting at the slot.
+fn add_leaves(tree: &Tree, slot: Slot, captures: &[&str]) {
+    captures.iter().enumerate().for_each(|(i, capture)| {
+        tree.add_leaf(slot.index + i, slot.repeated, capture);
+    });
 }
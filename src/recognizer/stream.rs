@@ -0,0 +1,260 @@
+//! Recognition of an input that arrives in chunks.
+//!
+//! A transition is only taken when more input can not change the match of its state, which is known when the
+//! anchored automaton of the regular expressions of the state can not continue on the input that is buffered, as the
+//! first matching alternative is then found. Only the input after the last transition is buffered, so the memory of a
+//! stream is the stack of the nested blocks it is in plus the longest symbol of the input.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead},
+    str,
+};
+
+use regex_automata::{
+    hybrid::{
+        dfa::{Cache, DFA},
+        LazyStateID,
+    },
+    util::start,
+    Anchored,
+};
+
+use super::{recognizer_automaton::Run, Recognizer, State};
+
+/// A recognizer that reads an input in chunks, which is made by [`Recognizer::stream`].
+#[derive(Debug)]
+pub struct RecognizerStream<'a> {
+    recognizer: &'a Recognizer,
+    run: Run,
+    /// The input that was not read by a transition yet.
+    buffer: String,
+    /// The number of bytes read before the buffer.
+    offset: usize,
+    /// The offset at which the input is rejected, once that is known.
+    rejected: Option<usize>,
+    /// The lazy automaton of the regular expressions of every state, if it could be built.
+    automata: HashMap<State, Option<(DFA, Cache)>>,
+    /// The state of the automaton of the current state after the start of the buffer, and the length of that start.
+    scanned: Option<(LazyStateID, usize)>,
+}
+
+impl Recognizer {
+    /// Starts recognizing an input that is given in chunks with [`RecognizerStream::feed`].
+    ///
+    /// A symbol can be split over chunks, as the input is only read when the chunks after it can not change how it
+    /// is read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vpl_parser_generator::Recognizer;
+    ///
+    /// let grammar = "Stmt:\n  Expr=E \";\" -> E\nExpr:\n  \"[0-9]+\" -> \"n\"\n  [\"\\(\" Expr=E \"\\)\"] -> E";
+    /// let recognizer = Recognizer::new(grammar).unwrap();
+    /// let mut stream = recognizer.stream();
+    /// assert_eq!(stream.feed("((12"), None);
+    /// assert_eq!(stream.depth(), 3);
+    /// assert_eq!(stream.feed("3));"), None);
+    /// assert_eq!(stream.finish(), None);
+    ///
+    /// let mut stream = recognizer.stream();
+    /// assert_eq!(stream.feed("((1]"), Some(3));
+    /// assert_eq!(stream.finish(), Some(3));
+    /// ```
+    pub fn stream(&self) -> RecognizerStream<'_> {
+        RecognizerStream {
+            recognizer: self,
+            run: Run::new(1),
+            buffer: String::new(),
+            offset: 0,
+            rejected: None,
+            automata: HashMap::new(),
+            scanned: None,
+        }
+    }
+
+    /// Recognizes the input of a reader, and gives the byte offset at which it is rejected, like
+    /// [`Recognizer::rejected_at`].
+    ///
+    /// The input is read in the chunks of the reader, and reading stops when the input is rejected. This gives an
+    /// error if the reader fails, or if the input is not UTF-8.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::BufReader;
+    /// use vpl_parser_generator::Recognizer;
+    ///
+    /// let grammar = "Stmt:\n  Expr=E \";\" -> E\nExpr:\n  \"[0-9]+\" -> \"n\"\n  [\"\\(\" Expr=E \"\\)\"] -> E";
+    /// let recognizer = Recognizer::new(grammar).unwrap();
+    /// let reader = BufReader::with_capacity(2, "((42));".as_bytes());
+    /// assert_eq!(recognizer.recognize_reader(reader).unwrap(), None);
+    /// let reader = BufReader::with_capacity(2, "((42)".as_bytes());
+    /// assert_eq!(recognizer.recognize_reader(reader).unwrap(), Some(5));
+    /// assert!(recognizer.recognize_reader(&[b'(', 0xff][..]).is_err());
+    /// ```
+    pub fn recognize_reader<R: BufRead>(&self, mut reader: R) -> io::Result<Option<usize>> {
+        let mut stream = self.stream();
+        // The start of a character that is split over chunks.
+        let mut pending = Vec::new();
+        loop {
+            let chunk = reader.fill_buf()?;
+            if chunk.is_empty() {
+                break;
+            }
+            pending.extend_from_slice(chunk);
+            let length = chunk.len();
+            reader.consume(length);
+            let valid = match str::from_utf8(&pending) {
+                Ok(text) => text.len(),
+                Err(error) if error.error_len().is_none() => error.valid_up_to(),
+                Err(error) => return Err(io::Error::new(io::ErrorKind::InvalidData, error)),
+            };
+            let text = str::from_utf8(&pending[..valid]).expect("the start is valid UTF-8");
+            if let Some(offset) = stream.feed(text) {
+                return Ok(Some(offset));
+            }
+            pending.drain(..valid);
+        }
+        if !pending.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "The input ends within a character"));
+        }
+        Ok(stream.finish())
+    }
+}
+
+impl RecognizerStream<'_> {
+    /// Adds a chunk of the input, and gives the byte offset at which the input is rejected, if that is known.
+    ///
+    /// Once the input is rejected, further chunks are ignored.
+    pub fn feed(&mut self, chunk: &str) -> Option<usize> {
+        if self.rejected.is_none() {
+            self.buffer.push_str(chunk);
+            self.read(false);
+        }
+        self.rejected
+    }
+
+    /// Ends the input, and gives the byte offset at which it is rejected, or `None` if it is recognized.
+    pub fn finish(mut self) -> Option<usize> {
+        if self.rejected.is_some() {
+            return self.rejected;
+        }
+        self.read(true);
+        if self.rejected.is_none() && !(self.run.accepting() && self.buffer.is_empty()) {
+            self.rejected = Some(self.offset);
+        }
+        self.rejected
+    }
+
+    /// Gives the number of bytes of the input that were read by transitions.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Gives the number of nested blocks and identifiers the input is in, after the input that was read.
+    pub fn depth(&self) -> usize {
+        self.run.stack.len()
+    }
+
+    /// Gives the number of bytes of the input that are buffered, as they can still be read in more than one way.
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Takes transitions while they can not change with more input, or until the recognizer is stuck at the end.
+    fn read(&mut self, end: bool) {
+        while self.rejected.is_none() && (end || self.decided()) {
+            let Some((rest, ..)) = self.recognizer.next_state(&mut self.run, &self.buffer) else {
+                if !end {
+                    self.rejected = Some(self.offset);
+                }
+                return;
+            };
+            let length = self.buffer.len() - rest.len();
+            self.buffer.drain(..length);
+            self.offset += length;
+            self.scanned = None;
+        }
+    }
+
+    /// Checks if the transition of the current state is the same for every input that starts with the buffer.
+    fn decided(&mut self) -> bool {
+        let recognizer = self.recognizer;
+        let state = self.run.state;
+        if state == 0 && self.run.stack.last().is_some_and(|point| recognizer.exits.contains_key(point)) {
+            return true;
+        }
+        let Some((regex, _)) = recognizer.next_state.get(&state) else {
+            return true;
+        };
+        let automaton = self.automata.entry(state).or_insert_with(|| {
+            let dfa = DFA::builder()
+                .configure(DFA::config().unicode_word_boundary(true))
+                .build(regex.regex.as_str())
+                .ok()?;
+            let cache = dfa.create_cache();
+            Some((dfa, cache))
+        });
+        let Some((dfa, cache)) = automaton else {
+            return false;
+        };
+        let (mut id, mut length) = match self.scanned {
+            Some(scanned) => scanned,
+            None => match dfa.start_state(cache, &start::Config::new().anchored(Anchored::Yes)) {
+                Ok(id) => (id, 0),
+                Err(_) => return false,
+            },
+        };
+        for &byte in &self.buffer.as_bytes()[length..] {
+            if id.is_dead() {
+                return true;
+            }
+            if id.is_quit() {
+                break;
+            }
+            id = match dfa.next_state(cache, id, byte) {
+                Ok(id) => id,
+                Err(_) => return false,
+            };
+            length += 1;
+        }
+        self.scanned = Some((id, length));
+        // A match is known one byte after it ends, and it is the first one if no byte can continue another match.
+        id.is_dead()
+            || id.is_match()
+                && (0..=u8::MAX).all(|byte| dfa.next_state(cache, id, byte).is_ok_and(|next| next.is_dead()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Recognizer, Sampler};
+
+    #[test]
+    fn chunks_are_rejected_like_the_whole_input() {
+        let grammar = "Stmt:\n  Expr=E \";\" -> E\nExpr:\n  \"[0-9]+\" -> \"n\"\n  [\"\\(\" Expr=E \"\\)\"] -> E\n  \
+                       [\"\\[\" Expr=E \"\\]\"] -> E";
+        let recognizer = Recognizer::new(grammar).unwrap();
+        let mut sampler = Sampler::new(grammar).unwrap().with_seed(3);
+        for _ in 0..10 {
+            let accepted = sampler.accepted().unwrap();
+            let near_miss = sampler.near_miss().unwrap();
+            for input in [&accepted, &near_miss, &accepted[..accepted.len() / 2]] {
+                let expected = recognizer.rejected_at(input);
+                for split in 0..=input.len() {
+                    let mut stream = recognizer.stream();
+                    let fed = stream.feed(&input[..split]).or(stream.feed(&input[split..]));
+                    assert!(fed.is_none() || fed == expected);
+                    assert_eq!(stream.finish(), expected);
+                }
+                let mut stream = recognizer.stream();
+                for index in 0..input.len() {
+                    stream.feed(&input[index..index + 1]);
+                }
+                assert_eq!(stream.finish(), expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,293 @@
+//! Enumeration of the shortest words that a nonterminal accepts.
+//!
+//! The automaton is searched from the starting state of the nonterminal with an explicit stack, and a transition
+//! reads a shortest string of every set of regular expressions that match the same strings, like
+//! [`Recognizer::is_empty`]. The search is ordered by the length of the input so far plus the length of the shortest
+//! way to the end, which is computed ahead from summaries of the nested words, so the words are found in the order
+//! of their length without searching longer inputs first.
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeSet, BinaryHeap, HashMap, HashSet},
+    error::Error,
+};
+
+use super::{recognizer_automaton::Action, Recognizer, State, Tree};
+use crate::regex::{matches_empty, minterms};
+
+/// A state of the search, with the return points on the stack and the letters read so far.
+type Configuration = (State, Vec<State>, Vec<usize>);
+
+impl Recognizer {
+    /// Gives up to `limit` inputs that a nonterminal accepts, shortest first, each with its parse tree.
+    ///
+    /// There is one input for every sequence of symbols, where a symbol is a set of regular expressions that match
+    /// the same strings, and every symbol is read as a shortest string it matches. An input of the automaton that
+    /// the recognizer rejects, as it reads it with the first matching alternative, is left out. The automaton can
+    /// have many more of these than the recognizer accepts, so the enumeration stops after `rejected` of them, and
+    /// then gives fewer than `limit` inputs. This gives an error if the nonterminal is not defined.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vpl_parser_generator::Recognizer;
+    ///
+    /// let grammar = "A:\n  [\"\\(\" A=A \"\\)\"] -> A\n  B=B -> B\nB:\n  \"a\" -> \"a\"\n  \"b+\" -> \"b\"";
+    /// let recognizer = Recognizer::new(grammar).unwrap();
+    /// let words: Vec<String> = recognizer.shortest_words("A", 5, 100).unwrap().into_iter().map(|(w, _)| w).collect();
+    /// assert_eq!(words, ["a", "b", "(a)", "(b)", "((a))"]);
+    /// let (word, tree) = &recognizer.shortest_words("B", 1, 100).unwrap()[0];
+    /// assert_eq!(word, "a");
+    /// assert_eq!(recognizer.nonterminals()[tree.borrow().identifier], "B");
+    /// assert!(recognizer.shortest_words("C", 1, 100).is_err());
+    /// ```
+    pub fn shortest_words(
+        &self,
+        nonterminal: &str,
+        limit: usize,
+        rejected: usize,
+    ) -> Result<Vec<(String, Tree)>, Box<dyn Error>> {
+        let start = self
+            .names
+            .iter()
+            .position(|name| name == nonterminal)
+            .ok_or_else(|| format!("Word {nonterminal} is not defined"))?
+            + 1;
+        let mut search = Search::new(self, start)?;
+        let recognizer = self.without_coverage();
+        let mut words = Vec::new();
+        let mut found = HashSet::new();
+        let mut skipped = 0;
+        while words.len() < limit && skipped <= rejected {
+            let Some(word) = search.next() else {
+                break;
+            };
+            if !found.insert(word.clone()) {
+                continue;
+            }
+            match recognizer.parse_nonterminal(start, &word) {
+                Some((tree, _)) => words.push((word, tree)),
+                None => skipped += 1,
+            }
+        }
+        Ok(words)
+    }
+}
+
+/// A search of the words of the automaton, in the order of their length.
+struct Search<'a> {
+    recognizer: &'a Recognizer,
+    /// The regular expressions of the transitions.
+    symbols: HashMap<&'a str, usize>,
+    /// The shortest strings that match the same set of regular expressions.
+    letters: Vec<String>,
+    letters_of: Vec<Vec<usize>>,
+    /// The length of the shortest word from every state to the end of the nonterminal it is part of.
+    to_end: HashMap<State, usize>,
+    /// The length of the shortest word from popping every return point to the end of the nonterminal it is part of.
+    after_pop: HashMap<State, usize>,
+    configurations: Vec<Configuration>,
+    queue: BinaryHeap<Reverse<(usize, usize)>>,
+    visited: HashSet<Configuration>,
+}
+
+impl<'a> Search<'a> {
+    fn new(recognizer: &'a Recognizer, start: State) -> Result<Self, String> {
+        let mut regexes = BTreeSet::new();
+        for (regex, actions) in recognizer.next_state.values() {
+            for (alternative, action) in regex.alternatives.iter().zip(actions) {
+                if matches!(action, Action::Call(..) | Action::Next(..) | Action::Return(_)) {
+                    regexes.insert(alternative.as_str());
+                }
+            }
+        }
+        let regexes: Vec<&str> = regexes.into_iter().collect();
+        let symbols = regexes.iter().enumerate().map(|(symbol, regex)| (*regex, symbol)).collect();
+        let mut letters = Vec::new();
+        let mut letters_of = vec![Vec::new(); regexes.len()];
+        for (matching, witness) in minterms(&regexes)? {
+            for symbol in matching {
+                letters_of[symbol].push(letters.len());
+            }
+            letters.push(witness);
+        }
+        let mut search = Search {
+            recognizer,
+            symbols,
+            letters,
+            letters_of,
+            to_end: HashMap::new(),
+            after_pop: HashMap::new(),
+            configurations: Vec::new(),
+            queue: BinaryHeap::new(),
+            visited: HashSet::new(),
+        };
+        search.compute_distances();
+        search.push((start, Vec::new(), Vec::new()));
+        Ok(search)
+    }
+
+    /// Gives the length of the shortest string of a regular expression of a transition.
+    fn shortest(&self, regex: &str) -> usize {
+        self.symbols.get(regex).map_or(usize::MAX, |symbol| {
+            self.letters_of[*symbol]
+                .iter()
+                .map(|letter| self.letters[*letter].len())
+                .min()
+                .unwrap_or(usize::MAX)
+        })
+    }
+
+    /// Computes the lengths of the shortest ways to the end, by repeating until nothing changes.
+    fn compute_distances(&mut self) {
+        let recognizer = self.recognizer;
+        self.to_end.insert(0, 0);
+        loop {
+            let mut changed = false;
+            for (state, (regex, actions)) in recognizer.next_state.iter() {
+                if *state == 0 {
+                    continue;
+                }
+                let mut distance = usize::MAX;
+                for (alternative, action) in regex.alternatives.iter().zip(actions) {
+                    let to_end = |state: &State| self.to_end.get(state).copied().unwrap_or(usize::MAX);
+                    let after_pop = |point: &State| self.after_pop.get(point).copied().unwrap_or(usize::MAX);
+                    let option = match action {
+                        Action::Call(point, next, _, _) => self
+                            .shortest(alternative)
+                            .saturating_add(to_end(next))
+                            .saturating_add(after_pop(point)),
+                        Action::Enter(point, next, _) => to_end(next).saturating_add(after_pop(point)),
+                        Action::Next(next, _) if matches_empty(alternative) => to_end(next),
+                        Action::Next(next, _) => self.shortest(alternative).saturating_add(to_end(next)),
+                        Action::Skip(next) => to_end(next),
+                        Action::Return(_) => usize::MAX,
+                    };
+                    distance = distance.min(option);
+                }
+                if distance < self.to_end.get(state).copied().unwrap_or(usize::MAX) {
+                    self.to_end.insert(*state, distance);
+                    changed = true;
+                }
+            }
+            for (point, next) in recognizer.exits.iter() {
+                let distance = self.to_end.get(next).copied().unwrap_or(usize::MAX);
+                if distance < self.after_pop.get(point).copied().unwrap_or(usize::MAX) {
+                    self.after_pop.insert(*point, distance);
+                    changed = true;
+                }
+            }
+            if let Some((regex, actions)) = recognizer.next_state.get(&0) {
+                for (alternative, action) in regex.alternatives.iter().zip(actions) {
+                    let Action::Return(map) = action else {
+                        continue;
+                    };
+                    for (point, (next, _)) in map {
+                        let to_end = self.to_end.get(next).copied().unwrap_or(usize::MAX);
+                        let distance = self.shortest(alternative).saturating_add(to_end);
+                        if distance < self.after_pop.get(point).copied().unwrap_or(usize::MAX) {
+                            self.after_pop.insert(*point, distance);
+                            changed = true;
+                        }
+                    }
+                }
+            }
+            if !changed {
+                return;
+            }
+        }
+    }
+
+    /// Adds a configuration to the search, unless it was added before or can not reach the end.
+    fn push(&mut self, configuration: Configuration) {
+        let (state, stack, letters) = &configuration;
+        let length: usize = letters.iter().map(|letter| self.letters[*letter].len()).sum();
+        let rest = stack.iter().fold(
+            self.to_end.get(state).copied().unwrap_or(usize::MAX),
+            |rest, point| rest.saturating_add(self.after_pop.get(point).copied().unwrap_or(usize::MAX)),
+        );
+        if rest == usize::MAX || !self.visited.insert(configuration.clone()) {
+            return;
+        }
+        self.queue.push(Reverse((length + rest, self.configurations.len())));
+        self.configurations.push(configuration);
+    }
+
+    /// Adds the configurations after reading every letter of a regular expression.
+    fn push_letters(&mut self, regex: &str, state: State, stack: &[State], letters: &[usize]) {
+        let Some(symbol) = self.symbols.get(regex) else {
+            return;
+        };
+        for letter in self.letters_of[*symbol].clone() {
+            let mut letters = letters.to_vec();
+            letters.push(letter);
+            self.push((state, stack.to_vec(), letters));
+        }
+    }
+
+    /// Gives the next word of the automaton, which is at least as long as the words before.
+    fn next(&mut self) -> Option<String> {
+        let recognizer = self.recognizer;
+        while let Some(Reverse((_, index))) = self.queue.pop() {
+            let (state, stack, letters) = self.configurations[index].clone();
+            if state == 0 {
+                let Some((point, rest)) = stack.split_last() else {
+                    return Some(letters.iter().map(|letter| self.letters[*letter].as_str()).collect());
+                };
+                if let Some(next) = recognizer.exits.get(point) {
+                    self.push((*next, rest.to_vec(), letters));
+                    continue;
+                }
+            }
+            let Some((regex, actions)) = recognizer.next_state.get(&state) else {
+                continue;
+            };
+            for (alternative, action) in regex.alternatives.iter().zip(actions) {
+                match action {
+                    Action::Call(point, next, _, _) => {
+                        let mut stack = stack.clone();
+                        stack.push(*point);
+                        self.push_letters(alternative, *next, &stack, &letters);
+                    }
+                    Action::Enter(point, next, _) => {
+                        let mut stack = stack.clone();
+                        stack.push(*point);
+                        self.push((*next, stack, letters.clone()));
+                    }
+                    Action::Next(next, _) => {
+                        if matches_empty(alternative) {
+                            self.push((*next, stack.clone(), letters.clone()));
+                        }
+                        self.push_letters(alternative, *next, &stack, &letters);
+                    }
+                    Action::Skip(next) => self.push((*next, stack.clone(), letters.clone())),
+                    Action::Return(map) => {
+                        let Some((point, rest)) = stack.split_last() else {
+                            continue;
+                        };
+                        if let Some((next, _)) = map.get(point) {
+                            self.push_letters(alternative, *next, rest, &letters);
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Recognizer;
+
+    #[test]
+    fn enumeration_stops_after_rejected_inputs() {
+        // The recognizer reads "ab" as "a", so it rejects the shorter input "abd" of the second rule.
+        let recognizer = Recognizer::new("A:\n  \"a\" \"bcc\" -> \"x\"\n  \"ab\" \"d\" -> \"y\"").unwrap();
+        let words = |rejected| -> Vec<String> {
+            recognizer.shortest_words("A", 10, rejected).unwrap().into_iter().map(|(word, _)| word).collect()
+        };
+        assert_eq!(words(1), ["abcc"]);
+        assert!(words(0).is_empty());
+    }
+}
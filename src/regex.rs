@@ -1,62 +1,257 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    ops::Range,
+};
 
 use regex::Regex;
+use regex_automata::{
+    dfa::{dense, Automaton, StartKind},
+    util::{primitives::StateID, start},
+    Anchored,
+};
+use regex_syntax::ast::{self, Ast};
 
+/// The spans of the named capture groups of a match, which are `None` for groups that did not participate.
+pub(crate) type Spans = Vec<Option<Range<usize>>>;
+
+/// Matches the start of an input against a list of alternative regular expressions.
+///
+/// The alternatives are tried in order, the first one that matches is chosen.
 #[derive(Debug, Clone)]
 pub struct RegexParser {
     pub regex: Regex,
-    pub size: usize,
-    pub captures: HashMap<String, Vec<String>>,
-    pub original_strings: Vec<String>,
+    /// The regular expressions of the alternatives, as they were given.
+    pub(crate) alternatives: Vec<String>,
+    /// The capture group of every alternative.
+    groups: Vec<usize>,
+    /// The named capture groups of every alternative, in the order of their capture index.
+    captures: Vec<Vec<usize>>,
+    /// The names of the named capture groups of every alternative.
+    names: Vec<Vec<String>>,
 }
 
 impl RegexParser {
-    pub fn parse<'a>(&self, input: &'a str) -> Option<(usize, &'a str, HashMap<String, String>)> {
+    /// Matches the start of the input and returns the index of the chosen alternative, the rest of the input and
+    /// the spans of the named capture groups of that alternative. Groups that did not participate have no span.
+    pub fn parse<'a>(&self, input: &'a str) -> Option<(usize, &'a str, Spans)> {
         let captures = self.regex.captures(input)?;
-        for i in 0..=self.size {
-            if let Some(capture) = captures.name(&format!("RESTRICTED_{i}")) {
-                let mut returned_captures: HashMap<String, String> = HashMap::new();
-                self.captures
-                    .get(&format!("RESTRICTED_{i}"))
-                    .unwrap()
+        for (i, group) in self.groups.iter().enumerate() {
+            if let Some(capture) = captures.get(*group) {
+                let returned_captures = self.captures[i]
                     .iter()
-                    .for_each(|name| {
-                        returned_captures.insert(
-                            name.clone(),
-                            captures.name(name).unwrap().as_str().to_owned(),
-                        );
-                    });
+                    .map(|group| captures.get(*group).map(|c| c.range()))
+                    .collect();
                 return Some((i, &input[capture.end()..], returned_captures));
             }
         }
         None
     }
+
+    /// Gives the names of the named capture groups of an alternative, in the order of its captures.
+    pub(crate) fn capture_names(&self, alternative: usize) -> &[String] {
+        &self.names[alternative]
+    }
 }
 
 impl From<Vec<String>> for RegexParser {
     fn from(regs: Vec<String>) -> Self {
-        let mut captures: HashMap<String, Vec<String>> = HashMap::new();
-        regs.iter().enumerate().for_each(|(i, r)| {
-            let regex = Regex::new(r).unwrap();
-            let mut map = Vec::new();
-            regex.capture_names().into_iter().for_each(|name| {
-                if let Some(name) = name {
-                    map.push(name.to_owned());
-                }
-            });
-            // map.push(format!("a{i}"));
-            captures.insert(format!("RESTRICTED_{i}"), map);
-        });
-        let mut res: String = format!(r"^((?P<RESTRICTED_0>{})", regs.get(0).unwrap());
-        for (i, reg) in regs.iter().enumerate().skip(1) {
-            res.extend(format!(r"|(?P<RESTRICTED_{i}>{reg})").chars());
+        Self::new(regs).unwrap()
+    }
+}
+
+impl RegexParser {
+    /// Combines the alternatives into one regular expression, or gives an error if an alternative is invalid.
+    pub(crate) fn new(regs: Vec<String>) -> Result<Self, regex::Error> {
+        let mut res: String = String::from("^(?:");
+        for (i, reg) in regs.iter().enumerate() {
+            if i > 0 {
+                res.push('|');
+            }
+            // Named groups are renamed, as the same name can be used in multiple alternatives.
+            res.extend(format!(r"(?P<RESTRICTED_{i}>{})", rename_captures(reg, i)).chars());
         }
         res.push(')');
-        Self {
-            regex: Regex::new(&res).unwrap(),
-            size: regs.len(),
+        let regex = Regex::new(&res)?;
+        let names: Vec<Option<&str>> = regex.capture_names().collect();
+        let index_of = |name: &str| names.iter().position(|n| *n == Some(name)).unwrap();
+        let groups = (0..regs.len())
+            .map(|i| index_of(&format!("RESTRICTED_{i}")))
+            .collect();
+        let capture_names: Vec<Vec<String>> = regs.iter().map(|reg| capture_names(reg)).collect();
+        let captures = capture_names
+            .iter()
+            .enumerate()
+            .map(|(i, names)| {
+                (0..names.len())
+                    .map(|j| index_of(&format!("RESTRICTED_{i}_{j}")))
+                    .collect()
+            })
+            .collect();
+        Ok(Self {
+            regex,
+            alternatives: regs,
+            groups,
             captures,
-            original_strings: regs,
+            names: capture_names,
+        })
+    }
+}
+
+/// Gives the names of the named capture groups of a regular expression, in the order of their capture index.
+pub fn capture_names(regex: &str) -> Vec<String> {
+    capture_name_spans(regex)
+        .into_iter()
+        .map(|span| regex[span.start.offset..span.end.offset].to_string())
+        .collect()
+}
+
+/// Checks if a regular expression can match the empty string, and thus can succeed without consuming input.
+///
+/// Invalid regular expressions are not considered to match the empty string.
+pub fn matches_empty(regex: &str) -> bool {
+    regex_syntax::Parser::new()
+        .parse(regex)
+        .is_ok_and(|hir| hir.properties().minimum_len() == Some(0))
+}
+
+/// A state of each of two DFAs that are run together.
+type StatePair = (StateID, StateID);
+
+/// Gives a shortest string that both regular expressions match entirely, if there is one.
+///
+/// Printable ASCII characters are tried first, so the string can be shown to the user. Regular expressions that are
+/// invalid, or that can not be compiled to a DFA, are not considered to match anything.
+pub(crate) fn intersection(first: &str, second: &str) -> Option<String> {
+    let (first, first_start) = full_match_dfa(first)?;
+    let (second, second_start) = full_match_dfa(second)?;
+    let bytes = byte_order();
+    // The pair of states every visited pair was first reached from, and the byte it was reached by.
+    let mut parents: HashMap<StatePair, Option<(StatePair, u8)>> = HashMap::new();
+    let mut queue = VecDeque::from([(first_start, second_start)]);
+    parents.insert((first_start, second_start), None);
+    while let Some(states @ (first_state, second_state)) = queue.pop_front() {
+        if first.is_match_state(first.next_eoi_state(first_state))
+            && second.is_match_state(second.next_eoi_state(second_state))
+        {
+            let mut witness = Vec::new();
+            let mut current = states;
+            while let Some(Some((parent, byte))) = parents.get(&current) {
+                witness.push(*byte);
+                current = *parent;
+            }
+            witness.reverse();
+            return Some(String::from_utf8_lossy(&witness).into_owned());
+        }
+        for &byte in &bytes {
+            let next = (
+                first.next_state(first_state, byte),
+                second.next_state(second_state, byte),
+            );
+            if first.is_dead_state(next.0) || second.is_dead_state(next.1) || parents.contains_key(&next) {
+                continue;
+            }
+            parents.insert(next, Some((states, byte)));
+            queue.push_back(next);
+        }
+    }
+    None
+}
+
+/// Gives the sets of regular expressions that entirely match the same string, each with a shortest such string.
+///
+/// Strings that are matched by the same set of regular expressions can not be told apart by them, so every set is
+/// a symbol of the alphabet the regular expressions split the strings in. Strings that none of them match, and the
+/// empty string, are left out.
+pub(crate) fn minterms(regexes: &[&str]) -> Result<Vec<(Vec<usize>, String)>, String> {
+    let dfas = regexes
+        .iter()
+        .map(|regex| full_match_dfa(regex).ok_or_else(|| format!("Regex {regex} can not be compiled to a DFA")))
+        .collect::<Result<Vec<_>, _>>()?;
+    let bytes = byte_order();
+    let start: Vec<StateID> = dfas.iter().map(|(_, start)| *start).collect();
+    let mut visited = HashSet::from([start.clone()]);
+    let mut queue = VecDeque::from([(start, Vec::new())]);
+    let mut found = HashSet::new();
+    let mut minterms = Vec::new();
+    while let Some((states, witness)) = queue.pop_front() {
+        let matching: Vec<usize> = dfas
+            .iter()
+            .zip(&states)
+            .enumerate()
+            .filter(|(_, ((dfa, _), state))| dfa.is_match_state(dfa.next_eoi_state(**state)))
+            .map(|(i, _)| i)
+            .collect();
+        if !matching.is_empty() && !witness.is_empty() && found.insert(matching.clone()) {
+            minterms.push((matching, String::from_utf8_lossy(&witness).into_owned()));
+        }
+        for &byte in &bytes {
+            let next: Vec<StateID> = dfas
+                .iter()
+                .zip(&states)
+                .map(|((dfa, _), state)| dfa.next_state(*state, byte))
+                .collect();
+            let dead = dfas.iter().zip(&next).all(|((dfa, _), state)| dfa.is_dead_state(*state));
+            if dead || !visited.insert(next.clone()) {
+                continue;
+            }
+            let mut witness = witness.clone();
+            witness.push(byte);
+            queue.push_back((next, witness));
         }
     }
-}
\ No newline at end of file
+    Ok(minterms)
+}
+
+/// Gives the bytes in the order they are tried for a string, printable ASCII characters first.
+fn byte_order() -> Vec<u8> {
+    (0x20..=0x7e).chain(0..0x20).chain(0x7f..=0xff).collect()
+}
+
+/// Compiles a regular expression to a DFA that only matches entire inputs, with its start state.
+fn full_match_dfa(regex: &str) -> Option<(dense::DFA<Vec<u32>>, StateID)> {
+    let dfa = dense::Builder::new()
+        .configure(dense::Config::new().start_kind(StartKind::Anchored))
+        .build(&format!(r"(?:{regex})\z"))
+        .ok()?;
+    let start = dfa
+        .start_state(&start::Config::new().anchored(Anchored::Yes))
+        .ok()?;
+    Some((dfa, start))
+}
+
+/// Renames the named capture groups of the alternative to `RESTRICTED_{alternative}_{j}`.
+fn rename_captures(regex: &str, alternative: usize) -> String {
+    let mut renamed = regex.to_string();
+    for (j, span) in capture_name_spans(regex).into_iter().enumerate().rev() {
+        renamed.replace_range(
+            span.start.offset..span.end.offset,
+            &format!("RESTRICTED_{alternative}_{j}"),
+        );
+    }
+    renamed
+}
+
+fn capture_name_spans(regex: &str) -> Vec<ast::Span> {
+    fn collect(ast: &Ast, spans: &mut Vec<ast::Span>) {
+        match ast {
+            Ast::Group(group) => {
+                if let ast::GroupKind::CaptureName { name, .. } = &group.kind {
+                    spans.push(name.span);
+                }
+                collect(&group.ast, spans);
+            }
+            Ast::Repetition(repetition) => collect(&repetition.ast, spans),
+            Ast::Alternation(alternation) => {
+                alternation.asts.iter().for_each(|ast| collect(ast, spans))
+            }
+            Ast::Concat(concat) => concat.asts.iter().for_each(|ast| collect(ast, spans)),
+            _ => {}
+        }
+    }
+    let mut spans = Vec::new();
+    if let Ok(ast) = ast::parse::Parser::new().parse(regex) {
+        collect(&ast, &mut spans);
+    }
+    spans.sort_by_key(|span| span.start.offset);
+    spans
+}
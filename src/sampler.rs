@@ -0,0 +1,419 @@
+use std::{collections::HashMap, error::Error, ops::Range};
+
+use regex_syntax::hir::{Class, Hir, HirKind};
+
+use crate::{ir::*, recognizer::Recognizer};
+
+/// The number of words that are generated for an input before giving up.
+const ATTEMPTS: usize = 100;
+/// The number of words that can be derived inside each other before an input is given up. The shortest
+/// continuation is chosen from half of it, so this is only reached by words whose shortest input is that deep.
+const MAX_RECURSION: usize = 500;
+
+/// Generates random inputs from a grammar, to fuzz a translator or the code that uses it.
+///
+/// The inputs are derived from the rules of the grammar, where every regular expression is replaced by a random
+/// string it matches. Once the input reaches the target length or nesting depth, the rules and repetitions that give
+/// the shortest input are chosen, so an input can be somewhat longer than the target. The recognizer of the grammar
+/// checks every input, as it reads the input with the first matching alternative and greedy repetitions, which can
+/// reject a derived input. The same seed gives the same inputs.
+pub struct Sampler {
+    grammar: Grammar,
+    recognizer: Recognizer,
+    random: Xorshift,
+    max_length: usize,
+    max_depth: usize,
+    /// The length of the shortest derivation of every nonterminal, counting every nonterminal as one.
+    shortest: Vec<usize>,
+    regexes: HashMap<String, Hir>,
+}
+
+/// A call or return symbol in a generated input, with the nested block it is part of.
+struct Symbol {
+    span: Range<usize>,
+    nested: Nested,
+    call: bool,
+}
+
+/// An input that is being derived, with its call and return symbols and the number of words it is in.
+struct Derivation {
+    input: String,
+    symbols: Vec<Symbol>,
+    level: usize,
+}
+
+impl Sampler {
+    /// Creates a sampler of the inputs of a grammar, with a target length of 100 bytes and nesting depth of 10.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vpl_parser_generator::{Recognizer, Sampler};
+    ///
+    /// let grammar = "A:\n  [\"\\(\" A=A \"\\)\"]* \"[a-z]\" -> A";
+    /// let mut sampler = Sampler::new(grammar).unwrap().with_seed(7).with_max_depth(3);
+    /// let recognizer = Recognizer::new(grammar).unwrap();
+    /// for _ in 0..10 {
+    ///     let input = sampler.accepted().unwrap();
+    ///     assert!(recognizer.recognize(&input).is_some());
+    ///     assert!(!input.contains("((((("));
+    /// }
+    /// ```
+    pub fn new(string: &str) -> Result<Self, Box<dyn Error + '_>> {
+        let grammar = crate::ast::Grammar::parse_complete(string)?;
+        let grammar = crate::elaborator::elaborate(grammar)?;
+        let recognizer = Recognizer::from(&grammar);
+        let mut sampler = Sampler {
+            shortest: vec![usize::MAX; grammar.nonterminals.len() + 1],
+            grammar,
+            recognizer,
+            random: Xorshift::new(0),
+            max_length: 100,
+            max_depth: 10,
+            regexes: HashMap::new(),
+        };
+        sampler.compute_shortest();
+        Ok(sampler)
+    }
+
+    /// Sets the seed of the random inputs.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.random = Xorshift::new(seed);
+        self
+    }
+
+    /// Sets the length in bytes after which the shortest continuation of an input is chosen.
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length;
+        self
+    }
+
+    /// Sets the nesting depth of nested blocks after which the shortest continuation of an input is chosen.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Generates a random input that the grammar accepts, or gives `None` if the first word derives no input, or if
+    /// none of 100 generated inputs is accepted by its recognizer.
+    pub fn accepted(&mut self) -> Option<String> {
+        (0..ATTEMPTS).find_map(|_| {
+            let (input, _) = self.generate()?;
+            self.recognizer.recognize(&input).map(|_| input)
+        })
+    }
+
+    /// Generates a random input that the grammar rejects, which differs from an accepted input in a single call or
+    /// return symbol: the symbol is left out, repeated, or replaced by the return symbol of another nested block.
+    ///
+    /// This gives `None` if the grammar has no nested blocks, if the first word derives no input, or if none of 100
+    /// mutated inputs is rejected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vpl_parser_generator::{Recognizer, Sampler};
+    ///
+    /// let grammar = "A:\n  [\"\\(\" A=A \"\\)\"]* \"[a-z]\" -> A\n  [\"<\" A=A \">\"] -> A";
+    /// let mut sampler = Sampler::new(grammar).unwrap().with_seed(42);
+    /// let recognizer = Recognizer::new(grammar).unwrap();
+    /// for _ in 0..10 {
+    ///     let input = sampler.near_miss().unwrap();
+    ///     assert!(recognizer.recognize(&input).is_none());
+    /// }
+    /// assert_eq!(Sampler::new("A:\n  \"a\" -> \"a\"").unwrap().near_miss(), None);
+    /// assert_eq!(Sampler::new("A:\n  [\"\\(\" A=A \"\\)\"] -> A").unwrap().near_miss(), None);
+    /// ```
+    pub fn near_miss(&mut self) -> Option<String> {
+        for _ in 0..ATTEMPTS {
+            let Some((input, symbols)) = self.generate() else {
+                continue;
+            };
+            if symbols.is_empty() {
+                continue;
+            }
+            let symbol = &symbols[self.random.below(symbols.len())];
+            let text = &input[symbol.span.clone()];
+            let replacement = match self.random.below(3) {
+                0 => String::new(),
+                1 => text.repeat(2),
+                _ => {
+                    let others = self.nested_blocks();
+                    let other = &others[self.random.below(others.len())];
+                    if symbol.call || other.return_symbol == symbol.nested.return_symbol {
+                        continue;
+                    }
+                    self.sample_regex(&other.return_symbol)
+                }
+            };
+            let mut mutated = input.clone();
+            mutated.replace_range(symbol.span.clone(), &replacement);
+            if self.recognizer.recognize(&mutated).is_none() {
+                return Some(mutated);
+            }
+        }
+        None
+    }
+
+    /// Gives the nested blocks of all rules.
+    fn nested_blocks(&self) -> Vec<Nested> {
+        fn collect(item: &RuleSourceItem, blocks: &mut Vec<Nested>) {
+            match item {
+                RuleSourceItem::Nested(nested) => blocks.push(nested.clone()),
+                RuleSourceItem::Repetition(repetition) => collect(&repetition.item, blocks),
+                _ => {}
+            }
+        }
+        let mut blocks = Vec::new();
+        for nonterminal in &self.grammar.nonterminals {
+            for rule in &nonterminal.rules {
+                rule.source.items.iter().for_each(|item| collect(item, &mut blocks));
+            }
+        }
+        blocks
+    }
+
+    /// Computes the length of the shortest derivation of every nonterminal, by repeating until nothing changes.
+    fn compute_shortest(&mut self) {
+        loop {
+            let mut changed = false;
+            for index in 0..self.grammar.nonterminals.len() {
+                let nonterminal = &self.grammar.nonterminals[index];
+                let shortest = nonterminal
+                    .rules
+                    .iter()
+                    .map(|rule| self.rule_length(rule))
+                    .min()
+                    .unwrap_or(usize::MAX);
+                if shortest < self.shortest[nonterminal.identifier] {
+                    self.shortest[nonterminal.identifier] = shortest;
+                    changed = true;
+                }
+            }
+            if !changed {
+                return;
+            }
+        }
+    }
+
+    fn rule_length(&self, rule: &Rule) -> usize {
+        rule.source
+            .items
+            .iter()
+            .fold(1, |length, item| length.saturating_add(self.item_length(item)))
+    }
+
+    fn item_length(&self, item: &RuleSourceItem) -> usize {
+        let regex_length = |regex: &str| {
+            regex_syntax::Parser::new()
+                .parse(regex)
+                .ok()
+                .and_then(|hir| hir.properties().minimum_len())
+                .unwrap_or(0)
+        };
+        match item {
+            RuleSourceItem::RegexString(regex) => regex_length(regex),
+            RuleSourceItem::Identifier(identifier) => self.shortest[identifier.source],
+            RuleSourceItem::Nested(nested) => self.shortest[nested.nonterminal.source]
+                .saturating_add(regex_length(&nested.call_symbol))
+                .saturating_add(regex_length(&nested.return_symbol)),
+            RuleSourceItem::Repetition(repetition) if repetition.kind == RepetitionKind::OneOrMore => {
+                self.item_length(&repetition.item)
+            }
+            RuleSourceItem::Repetition(_) => 0,
+        }
+    }
+
+    /// Derives a random input from the first nonterminal, with the call and return symbols in it, or gives `None`
+    /// if the first nonterminal derives no input or the derivation recurses too deep.
+    fn generate(&mut self) -> Option<(String, Vec<Symbol>)> {
+        if self.shortest[1] == usize::MAX {
+            return None;
+        }
+        let mut derivation = Derivation {
+            input: String::new(),
+            symbols: Vec::new(),
+            level: 0,
+        };
+        self.derive(1, 0, &mut derivation)?;
+        Some((derivation.input, derivation.symbols))
+    }
+
+    /// Derives a nonterminal that derives an input, choosing only rules that derive an input.
+    fn derive(&mut self, identifier: usize, depth: usize, derivation: &mut Derivation) -> Option<()> {
+        if derivation.level >= MAX_RECURSION {
+            return None;
+        }
+        derivation.level += 1;
+        let nonterminal = &self.grammar.nonterminals[identifier - 1];
+        let lengths: Vec<usize> = nonterminal.rules.iter().map(|rule| self.rule_length(rule)).collect();
+        let finite: Vec<usize> = (0..lengths.len()).filter(|index| lengths[*index] < usize::MAX).collect();
+        let index = if self.exhausted(depth, derivation) {
+            *finite.iter().min_by_key(|index| lengths[**index])?
+        } else {
+            *finite.get(self.random.below(finite.len().max(1)))?
+        };
+        let items = nonterminal.rules[index].source.items.clone();
+        for item in &items {
+            self.derive_item(item, depth, derivation)?;
+        }
+        derivation.level -= 1;
+        Some(())
+    }
+
+    fn derive_item(&mut self, item: &RuleSourceItem, depth: usize, derivation: &mut Derivation) -> Option<()> {
+        match item {
+            RuleSourceItem::RegexString(regex) => {
+                let string = self.sample_regex(regex);
+                derivation.input.push_str(&string);
+            }
+            RuleSourceItem::Identifier(identifier) => self.derive(identifier.source, depth, derivation)?,
+            RuleSourceItem::Nested(nested) => {
+                let start = derivation.input.len();
+                derivation.input.push_str(&self.sample_regex(&nested.call_symbol));
+                derivation.symbols.push(Symbol {
+                    span: start..derivation.input.len(),
+                    nested: nested.clone(),
+                    call: true,
+                });
+                self.derive(nested.nonterminal.source, depth + 1, derivation)?;
+                let start = derivation.input.len();
+                derivation.input.push_str(&self.sample_regex(&nested.return_symbol));
+                derivation.symbols.push(Symbol {
+                    span: start..derivation.input.len(),
+                    nested: nested.clone(),
+                    call: false,
+                });
+            }
+            RuleSourceItem::Repetition(repetition) => {
+                let minimum = usize::from(repetition.kind == RepetitionKind::OneOrMore);
+                // An item that derives no input, like a word that never ends, is left out of an optional repetition.
+                let maximum = match repetition.kind {
+                    _ if self.item_length(&repetition.item) == usize::MAX => 0,
+                    RepetitionKind::Optional => 1,
+                    _ => 3,
+                };
+                let mut count = 0;
+                while count < minimum
+                    || (count < maximum && !self.exhausted(depth, derivation) && self.random.below(2) == 0)
+                {
+                    self.derive_item(&repetition.item, depth, derivation)?;
+                    count += 1;
+                }
+            }
+        }
+        Some(())
+    }
+
+    /// Checks if the input has reached the target length or nesting depth, or the derivation half of its recursion
+    /// limit, after which it is finished as short as possible.
+    fn exhausted(&self, depth: usize, derivation: &Derivation) -> bool {
+        depth >= self.max_depth || derivation.input.len() >= self.max_length || derivation.level >= MAX_RECURSION / 2
+    }
+
+    /// Gives a random string that matches the regular expression.
+    fn sample_regex(&mut self, regex: &str) -> String {
+        let hir = self
+            .regexes
+            .entry(regex.to_string())
+            .or_insert_with(|| regex_syntax::Parser::new().parse(regex).unwrap_or_else(|_| Hir::empty()))
+            .clone();
+        let mut bytes = Vec::new();
+        self.sample_hir(&hir, &mut bytes);
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+
+    fn sample_hir(&mut self, hir: &Hir, bytes: &mut Vec<u8>) {
+        match hir.kind() {
+            HirKind::Empty | HirKind::Look(_) => {}
+            HirKind::Literal(literal) => bytes.extend_from_slice(&literal.0),
+            HirKind::Class(Class::Unicode(class)) => {
+                // Printable ASCII characters are chosen half of the time, so most inputs can be read.
+                let ranges: Vec<(u32, u32)> = class.ranges().iter().map(|r| (r.start() as u32, r.end() as u32)).collect();
+                let printable: Vec<(u32, u32)> = ranges
+                    .iter()
+                    .filter(|(start, end)| *start <= 0x7e && *end >= 0x20)
+                    .map(|(start, end)| (*start.max(&0x20), *end.min(&0x7e)))
+                    .collect();
+                let ranges = if !printable.is_empty() && self.random.below(2) == 0 { printable } else { ranges };
+                if ranges.is_empty() {
+                    return;
+                }
+                let (start, end) = ranges[self.random.below(ranges.len())];
+                let code = start + self.random.below((end - start) as usize + 1) as u32;
+                let c = char::from_u32(code).unwrap_or(char::from_u32(start).unwrap_or('?'));
+                bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+            }
+            HirKind::Class(Class::Bytes(class)) => {
+                let ranges = class.ranges();
+                if ranges.is_empty() {
+                    return;
+                }
+                let range = ranges[self.random.below(ranges.len())];
+                let byte = range.start() as usize + self.random.below((range.end() - range.start()) as usize + 1);
+                bytes.push(byte as u8);
+            }
+            HirKind::Repetition(repetition) => {
+                let minimum = repetition.min as usize;
+                let maximum = repetition.max.map_or(minimum + 3, |max| (max as usize).min(minimum + 3));
+                let count = minimum + self.random.below(maximum - minimum + 1);
+                (0..count).for_each(|_| self.sample_hir(&repetition.sub, bytes));
+            }
+            HirKind::Capture(capture) => self.sample_hir(&capture.sub, bytes),
+            HirKind::Concat(hirs) => hirs.iter().for_each(|hir| self.sample_hir(hir, bytes)),
+            HirKind::Alternation(hirs) => {
+                let hir = &hirs[self.random.below(hirs.len())];
+                self.sample_hir(hir, bytes);
+            }
+        }
+    }
+}
+
+/// The xorshift64* generator of random numbers, which is enough for generating inputs.
+struct Xorshift(u64);
+
+impl Xorshift {
+    fn new(seed: u64) -> Self {
+        // The state may not be zero, and a small seed is mixed so it does not start with small numbers.
+        Xorshift(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Gives a random number below the bound, which may not be zero.
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Sampler;
+
+    #[test]
+    fn words_that_derive_no_input_are_not_sampled() {
+        for grammar in ["A:\n  [\"\\(\" A=A \"\\)\"] -> A", "A:\n  \"a\" A=A -> A"] {
+            let mut sampler = Sampler::new(grammar).unwrap();
+            assert_eq!(sampler.accepted(), None);
+            assert_eq!(sampler.near_miss(), None);
+        }
+        let grammar = "A:\n  \"a\" [\"\\(\" B=B \"\\)\"]* -> join(B, \",\")\nB:\n  \"b\" B=B -> B";
+        let mut sampler = Sampler::new(grammar).unwrap();
+        assert_eq!(sampler.accepted(), Some("a".to_string()));
+    }
+
+    #[test]
+    fn deep_derivations_are_finished_as_short_as_possible() {
+        let grammar = "A:\n  \"a\" A=A -> A\n  \"b\" -> \"b\"";
+        let mut sampler = Sampler::new(grammar).unwrap().with_max_length(usize::MAX).with_max_depth(usize::MAX);
+        for _ in 0..100 {
+            let input = sampler.accepted().unwrap();
+            assert!(input.len() <= super::MAX_RECURSION);
+        }
+    }
+}
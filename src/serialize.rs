@@ -0,0 +1,660 @@
+//! Saving and loading of compiled recognizers and translators, in a binary format and as JSON.
+//!
+//! Both formats store the states of the automaton with the regular expressions of their alternatives, so loading
+//! only has to compile the regular expressions, and does not parse, check or build the grammar again. A file is
+//! refused if it was saved with another version of the format, or if its actions refer to states, nonterminals or
+//! children of a node that it does not have.
+
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    sync::Arc,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    codegen::tables,
+    functions::FunctionRegistry,
+    ir,
+    recognizer::{self, NextMap, Recognizer},
+    regex::{capture_names, RegexParser},
+    Translator,
+};
+
+/// Version of the format of saved recognizers and translators, which changes when older files can not be loaded.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Start of every binary file, followed by the version of the format.
+const MAGIC: &[u8; 4] = b"VPLA";
+const FORMAT_NAME: &str = "vpl-parser-generator";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Automaton {
+    format: String,
+    version: u32,
+    names: Vec<String>,
+    states: Vec<State>,
+    exits: Vec<(usize, usize)>,
+    /// The nonterminal and the index of the rule of the states that are part of a rule.
+    rules: Vec<(usize, usize, usize)>,
+    /// The transforms of the rules of every nonterminal, which are only saved for a translator.
+    transforms: Option<Vec<Vec<Vec<Transform>>>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct State {
+    state: usize,
+    alternatives: Vec<(String, Action)>,
+}
+
+/// An action with its return points in a vector, as saved.
+type Action = tables::Action<Vec<(usize, usize, tables::Slot)>>;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Transform {
+    String(String),
+    Identifier(usize),
+    Function { name: String, arguments: Vec<Transform> },
+    Join { index: usize, separator: String },
+}
+
+impl Recognizer {
+    /// Saves the compiled recognizer in a compact binary format, which can be loaded with [`Recognizer::load`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vpl_parser_generator::Recognizer;
+    ///
+    /// let recognizer = Recognizer::new("A:\n  \"a+\" -> \"b\"").unwrap();
+    /// let bytes = recognizer.save();
+    /// let loaded = Recognizer::load(&bytes).unwrap();
+    /// assert!(loaded.recognize("aaa").is_some());
+    /// assert!(Recognizer::load(b"VPLA\xff\x00\x00\x00").is_err());
+    /// ```
+    pub fn save(&self) -> Vec<u8> {
+        Automaton::new(self, None).to_bytes()
+    }
+
+    /// Loads a recognizer saved by [`Recognizer::save`] or [`Translator::save`], without building the grammar again.
+    pub fn load(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        Automaton::from_bytes(bytes)?.recognizer()
+    }
+
+    /// Saves the compiled recognizer as JSON, which can be loaded with [`Recognizer::from_json`].
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&Automaton::new(self, None)).unwrap()
+    }
+
+    /// Loads a recognizer saved by [`Recognizer::to_json`] or [`Translator::to_json`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vpl_parser_generator::Recognizer;
+    ///
+    /// let json = |action: &str| {
+    ///     let state = format!(r#"{{"state": 1, "alternatives": [["a", {action}]]}}"#);
+    ///     format!(r#"{{"format": "vpl-parser-generator", "version": 1, "names": ["A"], "states": [{state}], "exits": [], "rules": [], "transforms": null}}"#)
+    /// };
+    /// let loaded = Recognizer::from_json(&json(r#"{"next": [0, [0, false]]}"#)).unwrap();
+    /// assert!(loaded.recognize("a").is_some());
+    /// assert!(Recognizer::from_json(&json(r#"{"next": [7, [0, false]]}"#)).is_err());
+    /// assert!(Recognizer::from_json(&json(r#"{"next": [0, [1000000000000, false]]}"#)).is_err());
+    /// assert!(Recognizer::from_json(&json(r#"{"enter": [2, 1, [0, false]]}"#)).is_err());
+    /// ```
+    pub fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+        Automaton::from_json(json)?.recognizer()
+    }
+}
+
+impl Translator {
+    /// Saves the compiled translator in a compact binary format, which can be loaded with [`Translator::load`].
+    ///
+    /// The functions are not saved, they are given again when the translator is loaded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vpl_parser_generator::{FunctionRegistry, Translator};
+    ///
+    /// let translator = Translator::new("A:\n  \"(?P<v>[a-z]+)\" -> upper(v)").unwrap();
+    /// let loaded = Translator::load(&translator.save(), FunctionRegistry::default()).unwrap();
+    /// assert_eq!(loaded.translate("abc"), Some("ABC".to_string()));
+    /// let loaded = Translator::from_json(&translator.to_json(), FunctionRegistry::default()).unwrap();
+    /// assert_eq!(loaded.translate("abc"), Some("ABC".to_string()));
+    /// assert!(Translator::load(&translator.save(), FunctionRegistry::new()).is_err());
+    /// ```
+    pub fn save(&self) -> Vec<u8> {
+        Automaton::new(&self.recognizer, Some(&self.transforms)).to_bytes()
+    }
+
+    /// Loads a translator saved by [`Translator::save`], which can use the given functions in its transforms.
+    pub fn load(bytes: &[u8], functions: FunctionRegistry) -> Result<Self, Box<dyn Error>> {
+        Automaton::from_bytes(bytes)?.translator(functions)
+    }
+
+    /// Saves the compiled translator as JSON, which can be loaded with [`Translator::from_json`].
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&Automaton::new(&self.recognizer, Some(&self.transforms))).unwrap()
+    }
+
+    /// Loads a translator saved by [`Translator::to_json`], which can use the given functions in its transforms.
+    pub fn from_json(json: &str, functions: FunctionRegistry) -> Result<Self, Box<dyn Error>> {
+        Automaton::from_json(json)?.translator(functions)
+    }
+}
+
+impl Automaton {
+    fn new(recognizer: &Recognizer, transforms: Option<&Vec<Vec<ir::RuleTransform>>>) -> Self {
+        let mut states: Vec<State> = recognizer
+            .next_state
+            .iter()
+            .map(|(state, (regex, actions))| State {
+                state: *state,
+                alternatives: regex
+                    .alternatives
+                    .iter()
+                    .cloned()
+                    .zip(actions.iter().map(Action::from))
+                    .collect(),
+            })
+            .collect();
+        states.sort_by_key(|state| state.state);
+        let mut exits: Vec<(usize, usize)> = recognizer.exits.iter().map(|(p, n)| (*p, *n)).collect();
+        exits.sort();
+        let mut rules: Vec<(usize, usize, usize)> = recognizer
+            .state_to_rule
+            .iter()
+            .map(|(state, (nonterminal, rule))| (*state, *nonterminal, *rule))
+            .collect();
+        rules.sort();
+        Self {
+            format: FORMAT_NAME.to_string(),
+            version: FORMAT_VERSION,
+            names: recognizer.names.to_vec(),
+            states,
+            exits,
+            rules,
+            transforms: transforms.map(|transforms| {
+                transforms
+                    .iter()
+                    .map(|rules| {
+                        rules
+                            .iter()
+                            .map(|rule| rule.regs.iter().map(Transform::from).collect())
+                            .collect()
+                    })
+                    .collect()
+            }),
+        }
+    }
+
+    fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+        // The version is checked first, as the rest of the file can have another shape in other versions.
+        #[derive(Deserialize)]
+        struct Header {
+            format: String,
+            version: u32,
+        }
+        let header: Header = serde_json::from_str(json)?;
+        if header.format != FORMAT_NAME {
+            return Err("The file is not a saved recognizer or translator".into());
+        }
+        check_version(header.version)?;
+        Ok(serde_json::from_str(json)?)
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(self.version.to_le_bytes());
+        self.names.write(&mut bytes);
+        self.states.write(&mut bytes);
+        self.exits.write(&mut bytes);
+        self.rules.write(&mut bytes);
+        self.transforms.write(&mut bytes);
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let Some(rest) = bytes.strip_prefix(MAGIC) else {
+            return Err("The file is not a saved recognizer or translator".into());
+        };
+        let mut reader = Reader { bytes: rest };
+        let version = u32::from_le_bytes(reader.take(4)?.try_into().unwrap());
+        check_version(version)?;
+        let automaton = Self {
+            format: FORMAT_NAME.to_string(),
+            version,
+            names: Binary::read(&mut reader)?,
+            states: Binary::read(&mut reader)?,
+            exits: Binary::read(&mut reader)?,
+            rules: Binary::read(&mut reader)?,
+            transforms: Binary::read(&mut reader)?,
+        };
+        if !reader.bytes.is_empty() {
+            return Err("The saved automaton has unexpected bytes at the end".into());
+        }
+        Ok(automaton)
+    }
+
+    /// Checks that the actions only refer to states, nonterminals and return points of the automaton, that their
+    /// slots are within the children a node can have, and that a translator has a transform for every rule, as a
+    /// corrupt file could otherwise panic or allocate without bound while loading or parsing. Nothing is indexed
+    /// before this check.
+    fn check(&self) -> Result<(), String> {
+        let nonterminals = 1..=self.names.len();
+        let exits: HashMap<usize, usize> = self.exits.iter().copied().collect();
+        let mut states: HashSet<usize> = nonterminals.clone().collect();
+        states.insert(0);
+        states.extend(self.states.iter().map(|state| state.state));
+        states.extend(self.rules.iter().map(|(state, _, _)| *state));
+        // A node has a child for every alternative or named capture group of its rule at most.
+        let children: usize = self
+            .states
+            .iter()
+            .flat_map(|state| &state.alternatives)
+            .map(|(regex, _)| capture_names(regex).len() + 1)
+            .sum();
+        let check_state = |state: &usize| match states.contains(state) {
+            true => Ok(()),
+            false => Err(format!("The saved automaton refers to an unknown state {state}")),
+        };
+        let check_nonterminal = |nonterminal: &usize| match nonterminals.contains(nonterminal) {
+            true => Ok(()),
+            false => Err(format!("The saved automaton enters an unknown nonterminal {nonterminal}")),
+        };
+        let check_slot = |(index, _): &tables::Slot| match *index < children {
+            true => Ok(()),
+            false => Err(format!("The saved automaton places a child at {index}, but a node has at most {children}")),
+        };
+        for (_, action) in self.states.iter().flat_map(|state| &state.alternatives) {
+            match action {
+                Action::Call(point, nonterminal, captures, node) => {
+                    check_state(point)?;
+                    check_nonterminal(nonterminal)?;
+                    check_slot(captures)?;
+                    check_slot(node)?;
+                }
+                Action::Enter(point, nonterminal, node) => {
+                    if !exits.contains_key(point) {
+                        return Err(format!("The saved automaton has no exit for the return point {point}"));
+                    }
+                    check_nonterminal(nonterminal)?;
+                    check_slot(node)?;
+                }
+                Action::Next(next, captures) => {
+                    check_state(next)?;
+                    check_slot(captures)?;
+                }
+                Action::Skip(next) => check_state(next)?,
+                Action::Return(points) => {
+                    for (point, next, captures) in points {
+                        check_state(point)?;
+                        check_state(next)?;
+                        check_slot(captures)?;
+                    }
+                }
+            }
+        }
+        for (point, next) in &self.exits {
+            check_state(point)?;
+            check_state(next)?;
+        }
+        for (_, nonterminal, _) in &self.rules {
+            check_nonterminal(nonterminal)?;
+        }
+        if let Some(transforms) = &self.transforms {
+            // The transform of a node is chosen by its nonterminal and the alternative of its starting state.
+            let rules_known = transforms.len() == self.names.len()
+                && self
+                    .states
+                    .iter()
+                    .filter(|state| nonterminals.contains(&state.state))
+                    .all(|state| state.alternatives.len() <= transforms[state.state - 1].len());
+            if !rules_known {
+                return Err("The saved translator does not have a transform for every rule".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    fn recognizer(&self) -> Result<Recognizer, Box<dyn Error>> {
+        self.check()?;
+        let mut next_state = NextMap::new();
+        for state in &self.states {
+            let (regexes, actions): (Vec<String>, Vec<recognizer::Action>) = state
+                .alternatives
+                .iter()
+                .map(|(regex, action)| (regex.clone(), action.into()))
+                .unzip();
+            next_state.insert(state.state, (RegexParser::new(regexes)?, actions));
+        }
+        Ok(Recognizer {
+            next_state: Arc::new(next_state),
+            exits: Arc::new(self.exits.iter().copied().collect()),
+            names: Arc::new(self.names.clone()),
+            state_to_rule: Arc::new(
+                self.rules
+                    .iter()
+                    .map(|(state, nonterminal, rule)| (*state, (*nonterminal, *rule)))
+                    .collect(),
+            ),
+            coverage: None,
+        })
+    }
+
+    fn translator(&self, functions: FunctionRegistry) -> Result<Translator, Box<dyn Error>> {
+        let recognizer = self.recognizer()?;
+        let transforms: Vec<Vec<ir::RuleTransform>> = self
+            .transforms
+            .as_ref()
+            .ok_or("The file is a saved recognizer, not a translator")?
+            .iter()
+            .map(|rules| {
+                rules
+                    .iter()
+                    .map(|regs| ir::RuleTransform {
+                        regs: regs.iter().map(ir::RuleTransformItem::from).collect(),
+                    })
+                    .collect()
+            })
+            .collect();
+        Ok(Translator::from_parts(recognizer, transforms, functions)?)
+    }
+}
+
+fn check_version(version: u32) -> Result<(), String> {
+    if version != FORMAT_VERSION {
+        return Err(format!(
+            "The file is saved with version {version} of the format, but only version {FORMAT_VERSION} can be loaded"
+        ));
+    }
+    Ok(())
+}
+
+impl From<&ir::RuleTransformItem> for Transform {
+    fn from(item: &ir::RuleTransformItem) -> Self {
+        match item {
+            ir::RuleTransformItem::String(s) => Self::String(s.clone()),
+            ir::RuleTransformItem::Identifier(index) => Self::Identifier(*index),
+            ir::RuleTransformItem::Function(function) => Self::Function {
+                name: function.name.clone(),
+                arguments: function.arguments.iter().map(Transform::from).collect(),
+            },
+            ir::RuleTransformItem::Join(index, separator) => Self::Join {
+                index: *index,
+                separator: separator.clone(),
+            },
+        }
+    }
+}
+
+impl From<&Transform> for ir::RuleTransformItem {
+    fn from(transform: &Transform) -> Self {
+        match transform {
+            Transform::String(s) => Self::String(s.clone()),
+            Transform::Identifier(index) => Self::Identifier(*index),
+            Transform::Function { name, arguments } => Self::Function(ir::Function {
+                name: name.clone(),
+                arguments: arguments.iter().map(ir::RuleTransformItem::from).collect(),
+            }),
+            Transform::Join { index, separator } => Self::Join(*index, separator.clone()),
+        }
+    }
+}
+
+/// Remaining input of the binary format.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], String> {
+        if self.bytes.len() < length {
+            return Err("The saved automaton ends unexpectedly".to_string());
+        }
+        let (taken, rest) = self.bytes.split_at(length);
+        self.bytes = rest;
+        Ok(taken)
+    }
+}
+
+/// Values of the binary format. Numbers are unsigned LEB128, and lists and strings start with their length.
+trait Binary: Sized {
+    fn write(&self, bytes: &mut Vec<u8>);
+    fn read(reader: &mut Reader) -> Result<Self, String>;
+}
+
+impl Binary for usize {
+    fn write(&self, bytes: &mut Vec<u8>) {
+        let mut value = *self;
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                bytes.push(byte);
+                return;
+            }
+            bytes.push(byte | 0x80);
+        }
+    }
+    fn read(reader: &mut Reader) -> Result<Self, String> {
+        let mut value: usize = 0;
+        for shift in (0..usize::BITS).step_by(7) {
+            let byte = reader.take(1)?[0];
+            value |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("The saved automaton has a number that is too large".to_string())
+    }
+}
+
+impl Binary for bool {
+    fn write(&self, bytes: &mut Vec<u8>) {
+        bytes.push(*self as u8);
+    }
+    fn read(reader: &mut Reader) -> Result<Self, String> {
+        match reader.take(1)?[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            byte => Err(format!("The saved automaton has an invalid boolean {byte}")),
+        }
+    }
+}
+
+impl Binary for String {
+    fn write(&self, bytes: &mut Vec<u8>) {
+        self.len().write(bytes);
+        bytes.extend(self.as_bytes());
+    }
+    fn read(reader: &mut Reader) -> Result<Self, String> {
+        let length = usize::read(reader)?;
+        String::from_utf8(reader.take(length)?.to_vec())
+            .map_err(|_| "The saved automaton has a string that is not UTF-8".to_string())
+    }
+}
+
+impl<T: Binary> Binary for Vec<T> {
+    fn write(&self, bytes: &mut Vec<u8>) {
+        self.len().write(bytes);
+        self.iter().for_each(|value| value.write(bytes));
+    }
+    fn read(reader: &mut Reader) -> Result<Self, String> {
+        let length = usize::read(reader)?;
+        // Every value is at least one byte, so a corrupt length can not allocate more than the input.
+        let mut values = Vec::with_capacity(length.min(reader.bytes.len()));
+        for _ in 0..length {
+            values.push(T::read(reader)?);
+        }
+        Ok(values)
+    }
+}
+
+impl<T: Binary> Binary for Option<T> {
+    fn write(&self, bytes: &mut Vec<u8>) {
+        self.is_some().write(bytes);
+        if let Some(value) = self {
+            value.write(bytes);
+        }
+    }
+    fn read(reader: &mut Reader) -> Result<Self, String> {
+        if bool::read(reader)? {
+            Ok(Some(T::read(reader)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<A: Binary, B: Binary> Binary for (A, B) {
+    fn write(&self, bytes: &mut Vec<u8>) {
+        self.0.write(bytes);
+        self.1.write(bytes);
+    }
+    fn read(reader: &mut Reader) -> Result<Self, String> {
+        Ok((A::read(reader)?, B::read(reader)?))
+    }
+}
+
+impl<A: Binary, B: Binary, C: Binary> Binary for (A, B, C) {
+    fn write(&self, bytes: &mut Vec<u8>) {
+        self.0.write(bytes);
+        self.1.write(bytes);
+        self.2.write(bytes);
+    }
+    fn read(reader: &mut Reader) -> Result<Self, String> {
+        Ok((A::read(reader)?, B::read(reader)?, C::read(reader)?))
+    }
+}
+
+impl Binary for State {
+    fn write(&self, bytes: &mut Vec<u8>) {
+        self.state.write(bytes);
+        self.alternatives.write(bytes);
+    }
+    fn read(reader: &mut Reader) -> Result<Self, String> {
+        Ok(Self {
+            state: Binary::read(reader)?,
+            alternatives: Binary::read(reader)?,
+        })
+    }
+}
+
+impl Binary for Action {
+    fn write(&self, bytes: &mut Vec<u8>) {
+        match self {
+            Action::Call(point, nonterminal, captures, node) => {
+                bytes.push(0);
+                (*point, *nonterminal, *captures).write(bytes);
+                node.write(bytes);
+            }
+            Action::Enter(point, nonterminal, node) => {
+                bytes.push(1);
+                (*point, *nonterminal, *node).write(bytes);
+            }
+            Action::Next(state, captures) => {
+                bytes.push(2);
+                (*state, *captures).write(bytes);
+            }
+            Action::Skip(state) => {
+                bytes.push(3);
+                state.write(bytes);
+            }
+            Action::Return(points) => {
+                bytes.push(4);
+                points.write(bytes);
+            }
+        }
+    }
+    fn read(reader: &mut Reader) -> Result<Self, String> {
+        Ok(match reader.take(1)?[0] {
+            0 => {
+                let (point, nonterminal, captures) = Binary::read(reader)?;
+                Action::Call(point, nonterminal, captures, Binary::read(reader)?)
+            }
+            1 => {
+                let (point, nonterminal, node) = Binary::read(reader)?;
+                Action::Enter(point, nonterminal, node)
+            }
+            2 => {
+                let (state, captures) = Binary::read(reader)?;
+                Action::Next(state, captures)
+            }
+            3 => Action::Skip(Binary::read(reader)?),
+            4 => Action::Return(Binary::read(reader)?),
+            tag => return Err(format!("The saved automaton has an invalid action {tag}")),
+        })
+    }
+}
+
+impl Binary for Transform {
+    fn write(&self, bytes: &mut Vec<u8>) {
+        match self {
+            Transform::String(s) => {
+                bytes.push(0);
+                s.write(bytes);
+            }
+            Transform::Identifier(index) => {
+                bytes.push(1);
+                index.write(bytes);
+            }
+            Transform::Function { name, arguments } => {
+                bytes.push(2);
+                name.write(bytes);
+                arguments.write(bytes);
+            }
+            Transform::Join { index, separator } => {
+                bytes.push(3);
+                index.write(bytes);
+                separator.write(bytes);
+            }
+        }
+    }
+    fn read(reader: &mut Reader) -> Result<Self, String> {
+        Ok(match reader.take(1)?[0] {
+            0 => Transform::String(Binary::read(reader)?),
+            1 => Transform::Identifier(Binary::read(reader)?),
+            2 => Transform::Function {
+                name: Binary::read(reader)?,
+                arguments: Binary::read(reader)?,
+            },
+            3 => Transform::Join {
+                index: Binary::read(reader)?,
+                separator: Binary::read(reader)?,
+            },
+            tag => return Err(format!("The saved automaton has an invalid transform {tag}")),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FunctionRegistry, Translator};
+
+    #[test]
+    fn translators_without_transforms_are_refused() {
+        let mut bytes = b"VPLA".to_vec();
+        bytes.extend(1u32.to_le_bytes());
+        bytes.extend([1, 1, b'A']);
+        bytes.extend([1, 1, 1, 1, b'a', 2, 0, 0, 0]);
+        bytes.extend([0, 0]);
+        bytes.extend([1, 0]);
+        assert!(Translator::load(&bytes, FunctionRegistry::default()).is_err());
+
+        let state = r#"{"state": 1, "alternatives": [["a", {"next": [0, [0, false]]}]]}"#;
+        let json = format!(
+            r#"{{"format": "vpl-parser-generator", "version": 1, "names": ["A"], "states": [{state}], "exits": [], "rules": [], "transforms": []}}"#
+        );
+        assert!(Translator::from_json(&json, FunctionRegistry::default()).is_err());
+        let json = json.replace(r#""transforms": []"#, r#""transforms": [[]]"#);
+        assert!(Translator::from_json(&json, FunctionRegistry::default()).is_err());
+        let json = json.replace(r#""transforms": [[]]"#, r#""transforms": [[[{"string": "b"}]]]"#);
+        let translator = Translator::from_json(&json, FunctionRegistry::default()).unwrap();
+        assert_eq!(translator.translate("a"), Some("b".to_string()));
+    }
+}
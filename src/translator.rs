@@ -1,14 +1,18 @@
 use std::error::Error;
 
 use crate::{
+    elaborator::ElaborationResult,
+    functions::FunctionRegistry,
     ir::*,
-    recognizer::{Recognizer, ParseTree},
+    recognizer::{Child, Coverage, Recognizer, ParseTree},
 };
 
 #[derive(Debug)]
 pub struct Translator {
-    recognizer: Recognizer,
-    grammar: Grammar,
+    pub(crate) recognizer: Recognizer,
+    /// The transforms of the rules of every nonterminal.
+    pub(crate) transforms: Vec<Vec<RuleTransform>>,
+    functions: FunctionRegistry,
 }
 
 /// Struct that can translate an input text with a specified transformation.
@@ -26,29 +30,112 @@ impl Translator {
     /// use vpl_parser_generator::Translator;
     ///
     /// let string = fs::read_to_string("examples/a-lang.vpa").unwrap();
-    /// let mut translator = Translator::new(&string).unwrap();
+    /// let translator = Translator::new(&string).unwrap();
+    /// ```
+    ///
+    /// A nonterminal can use itself again after consuming input, but not before:
+    ///
+    /// ```
+    /// use vpl_parser_generator::Translator;
+    ///
+    /// let translator = Translator::new("List:\n  \"a\" List=L -> \"a,\" L\n  \"b\" -> \"b\"").unwrap();
+    /// assert_eq!(translator.translate("aab"), Some("a,a,b".to_string()));
+    /// assert!(Translator::new("List:\n  List=L \"a\" -> L\n  \"b\" -> \"b\"").is_err());
     /// ```
     pub fn new(string: &str) -> Result<Self, Box<dyn Error + '_>> {
-        let (rest_string, grammar) = crate::ast::Grammar::parse(string)?;
-        if !rest_string.is_empty() {
-            return Err("Error, entire file not parsed".into());
-        }
+        Self::with_functions(string, FunctionRegistry::default())
+    }
+
+    /// Creates a new VPL translator based on the input grammar, which can use the given functions in its transforms.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vpl_parser_generator::{FunctionRegistry, Translator};
+    ///
+    /// let grammar = "A:\n  \"(?P<v>[a-z<>]+)\" -> \"<b>\" html(upper(v)) \"</b>\"";
+    /// let translator = Translator::with_functions(grammar, FunctionRegistry::default()).unwrap();
+    /// assert_eq!(translator.translate("a<b"), Some("<b>A&lt;B</b>".to_string()));
+    /// assert!(Translator::with_functions(grammar, FunctionRegistry::new()).is_err());
+    /// ```
+    pub fn with_functions(
+        string: &str,
+        functions: FunctionRegistry,
+    ) -> Result<Self, Box<dyn Error + '_>> {
+        let grammar = crate::ast::Grammar::parse_complete(string)?;
         let elaborated = crate::elaborator::elaborate(grammar)?;
         let recognizer = crate::recognizer::Recognizer::from(&elaborated);
+        let transforms = elaborated
+            .nonterminals
+            .iter()
+            .map(|w| w.rules.iter().map(|r| r.transform.clone()).collect())
+            .collect();
+        Ok(Self::from_parts(recognizer, transforms, functions)?)
+    }
+
+    /// Creates a translator from a built recognizer and the transforms of its rules, after checking the functions.
+    pub(crate) fn from_parts(
+        recognizer: Recognizer,
+        transforms: Vec<Vec<RuleTransform>>,
+        functions: FunctionRegistry,
+    ) -> ElaborationResult<Self> {
+        crate::elaborator::check_functions(&transforms, &functions)?;
         Ok(Translator {
             recognizer,
-            grammar: elaborated,
+            transforms,
+            functions,
         })
     }
 
+    /// Gives the recognizer of the grammar, for example to find where an input that is not translated is rejected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vpl_parser_generator::Translator;
+    ///
+    /// let translator = Translator::new("A:\n  \"a\" -> \"x\"").unwrap();
+    /// assert_eq!(translator.translate("b"), None);
+    /// assert_eq!(translator.recognizer().rejected_at("b"), Some(0));
+    /// ```
+    pub fn recognizer(&self) -> &Recognizer {
+        &self.recognizer
+    }
+
+    /// Starts recording which rules are used by [`Translator::translate`], and thus which transforms, see
+    /// [`Recognizer::record_coverage`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vpl_parser_generator::Translator;
+    ///
+    /// let mut translator = Translator::new("A:\n  \"a\" -> \"x\"\n  \"b\" -> \"y\"").unwrap();
+    /// translator.record_coverage();
+    /// translator.translate("a");
+    /// translator.translate("a");
+    /// assert_eq!(translator.coverage().unwrap().rule_uses("A"), Some(vec![2, 0]));
+    /// ```
+    pub fn record_coverage(&mut self) {
+        self.recognizer.record_coverage();
+    }
+
+    /// Gives a copy of the coverage recorded since [`Translator::record_coverage`], if it is recording.
+    pub fn coverage(&self) -> Option<Coverage> {
+        self.recognizer.coverage()
+    }
+
+    /// Stops recording, and gives the recorded coverage.
+    pub fn take_coverage(&mut self) -> Option<Coverage> {
+        self.recognizer.take_coverage()
+    }
+
     fn terminal_size(&self) -> usize {
-        self.grammar
-            .nonterminals
+        self.transforms
             .iter()
-            .flat_map(|w| w.rules.iter())
-            .map(|r| {
-                r.transform
-                    .regs
+            .flatten()
+            .map(|t| {
+                t.regs
                     .iter()
                     .filter_map(|r| match r {
                         RuleTransformItem::String(s) => Some(s.len()),
@@ -59,11 +146,40 @@ impl Translator {
             .sum::<usize>()
     }
 
-    pub fn translate(&mut self, text: &str) -> Option<String> {
+    /// Translates an input text, or gives `None` if the text is not recognized.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vpl_parser_generator::Translator;
+    ///
+    /// let grammar = "List:\n  \"<ul>\" [\"<li>\" Item=I \"</li>\"]* \"</ul>\" -> \"[\" join(I, \", \") \"]\"\nItem:\n  \"(?P<t>[a-z]+)\" -> t";
+    /// let translator = Translator::new(grammar).unwrap();
+    /// assert_eq!(translator.translate("<ul><li>a</li><li>b</li></ul>"), Some("[a, b]".to_string()));
+    /// assert_eq!(translator.translate("<ul></ul>"), Some("[]".to_string()));
+    /// assert_eq!(translator.translate("<ul><li>a</li>"), None);
+    /// ```
+    ///
+    /// A translator can translate on multiple threads at the same time:
+    ///
+    /// ```
+    /// use std::thread;
+    /// use vpl_parser_generator::Translator;
+    ///
+    /// let translator = Translator::new("A:\n  \"(?P<v>[a-z]+)\" -> upper(v)").unwrap();
+    /// thread::scope(|scope| {
+    ///     let first = scope.spawn(|| translator.translate("abc"));
+    ///     let second = scope.spawn(|| translator.translate("def"));
+    ///     assert_eq!(first.join().unwrap(), Some("ABC".to_string()));
+    ///     assert_eq!(second.join().unwrap(), Some("DEF".to_string()));
+    /// });
+    /// ```
+    pub fn translate(&self, text: &str) -> Option<String> {
         let (parse_tree, mut size) = self.recognizer.parse(text)?;
         size += self.terminal_size();
         let mut current_node = parse_tree.clone();
-        let mut result: String = String::with_capacity(size);
+        // The last buffer receives the output, the others are arguments of functions that are being applied.
+        let mut buffers: Vec<String> = vec![String::with_capacity(size)];
         let mut queue: Vec<TranslateAction> = Vec::new();
         self.add_rule_source_items(&current_node, &mut queue);
         while let Some(action) = queue.pop() {
@@ -72,35 +188,74 @@ impl Translator {
                     current_node = current_node.parent().unwrap();
                 }
                 TranslateAction::ParseRuleTransformItem(RuleTransformItem::Identifier(index)) => {
-                    let value = current_node.child(index).unwrap();
-                    match value {
-                        crate::recognizer::Child::Node(n) => {
-                            current_node = n.clone();
-                            queue.push(TranslateAction::Parent);
-                            self.add_rule_source_items(&current_node, &mut queue);
-                        }
-                        crate::recognizer::Child::Leaf(l) => {
-                            result.push_str(&l);
+                    if let Some(child) = current_node.child(index) {
+                        queue.push(TranslateAction::Child(child));
+                    }
+                }
+                TranslateAction::ParseRuleTransformItem(RuleTransformItem::Join(index, separator)) => {
+                    match current_node.child(index) {
+                        Some(Child::List(children)) => {
+                            for (i, child) in children.into_iter().enumerate().rev() {
+                                queue.push(TranslateAction::Child(child));
+                                if i > 0 {
+                                    queue.push(TranslateAction::ParseRuleTransformItem(
+                                        RuleTransformItem::String(separator.clone()),
+                                    ));
+                                }
+                            }
                         }
+                        Some(child) => queue.push(TranslateAction::Child(child)),
+                        None => {}
                     }
                 }
+                TranslateAction::Child(Child::Node(n)) => {
+                    current_node = n;
+                    queue.push(TranslateAction::Parent);
+                    self.add_rule_source_items(&current_node, &mut queue);
+                }
+                TranslateAction::Child(Child::Leaf(l)) => {
+                    buffers.last_mut()?.push_str(&l);
+                }
+                TranslateAction::Child(Child::List(children)) => {
+                    children
+                        .into_iter()
+                        .rev()
+                        .for_each(|child| queue.push(TranslateAction::Child(child)));
+                }
                 TranslateAction::ParseRuleTransformItem(RuleTransformItem::String(s)) => {
-                    result.push_str(&s);
+                    buffers.last_mut()?.push_str(&s);
+                }
+                TranslateAction::ParseRuleTransformItem(RuleTransformItem::Function(function)) => {
+                    queue.push(TranslateAction::Apply(
+                        function.name,
+                        function.arguments.len(),
+                    ));
+                    function.arguments.into_iter().rev().for_each(|argument| {
+                        queue.push(TranslateAction::ParseRuleTransformItem(argument));
+                        queue.push(TranslateAction::Argument);
+                    });
+                }
+                TranslateAction::Argument => {
+                    buffers.push(String::new());
+                }
+                TranslateAction::Apply(name, arity) => {
+                    let arguments = buffers.split_off(buffers.len() - arity);
+                    let value = self.functions.apply(&name, &arguments)?;
+                    buffers.last_mut()?.push_str(&value);
                 }
             }
         }
-        Some(result)
+        buffers.pop()
     }
 
     /// Adds all rule transform items of the current node to the queue
     fn add_rule_source_items(
-        &mut self,
+        &self,
         current_node: &std::rc::Rc<std::cell::RefCell<crate::recognizer::Node>>,
         queue: &mut Vec<TranslateAction>,
     ) {
         let n = &mut *current_node.borrow_mut();
-        let rule = self.grammar.nonterminals[n.identifier].rules[n.rule_nr].clone();
-        rule.transform
+        self.transforms[n.identifier][n.rule_nr]
             .regs
             .iter()
             .rev()
@@ -111,4 +266,26 @@ impl Translator {
 enum TranslateAction {
     Parent,
     ParseRuleTransformItem(RuleTransformItem),
+    /// Translates a child of the current node.
+    Child(Child),
+    /// Starts translating the next argument of a function into a new buffer.
+    Argument,
+    /// Applies a function to the buffers of its arguments.
+    Apply(String, usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Recognizer, Translator};
+
+    #[test]
+    fn deep_trees_of_right_recursive_words() {
+        let grammar = "List:\n  \"a\" List=L -> \"a\" L\n  \"b\" -> \"b\"";
+        let input = "a".repeat(200_000) + "b";
+        let recognizer = Recognizer::new(grammar).unwrap();
+        let (tree, _) = recognizer.parse(&input).unwrap();
+        drop(tree);
+        let translator = Translator::new(grammar).unwrap();
+        assert_eq!(translator.translate(&input), Some(input));
+    }
 }
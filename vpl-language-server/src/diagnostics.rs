@@ -0,0 +1,111 @@
+//! The diagnostics of a grammar file, which are the errors of parsing and elaborating it, or its warnings.
+
+use lsp_types::{Diagnostic, DiagnosticSeverity, Position, Range};
+use vpl_parser_generator::{check_all, ErrorLocation, FunctionRegistry};
+
+use crate::document::{Document, Kind};
+
+/// Checks a grammar as the translator does, and gives its error at the place it is about, or its warnings at the
+/// definitions of their words.
+pub fn diagnostics(document: &Document) -> Vec<Diagnostic> {
+    let diagnostic = |range, severity, message| Diagnostic {
+        range,
+        severity: Some(severity),
+        source: Some("vpl".to_string()),
+        message,
+        ..Default::default()
+    };
+    let (warnings, overlaps) = match check_all(&document.text, &FunctionRegistry::default()) {
+        Ok(result) => result,
+        Err(error) => {
+            let range = locate(document, &error.location);
+            return vec![diagnostic(range, DiagnosticSeverity::ERROR, error.message)];
+        }
+    };
+    let warnings = warnings.iter().map(|warning| {
+        let range = document
+            .definition(&warning.word)
+            .map_or_else(|| document.line_range(0), |name| name.range);
+        diagnostic(range, DiagnosticSeverity::WARNING, warning.to_string())
+    });
+    let overlaps = overlaps.iter().map(|overlap| {
+        let range = document
+            .regexes
+            .iter()
+            .find(|regex| regex.text == overlap.second.regex)
+            .map_or_else(|| document.line_range(0), |regex| regex.range);
+        diagnostic(range, DiagnosticSeverity::WARNING, overlap.to_string())
+    });
+    warnings.chain(overlaps).collect()
+}
+
+/// Gives the range of the part of the grammar an error is about.
+///
+/// The word, rule, regex or function of the error is looked up in the document. If it can not be found, the error
+/// is reported on the first line.
+fn locate(document: &Document, location: &ErrorLocation) -> Range {
+    let definition = |word: &str| document.definitions.iter().find(|definition| definition.name == word);
+    let name = |word: &str, kind| document.named(word, kind).next().map(|name| name.range);
+    let range = match location {
+        ErrorLocation::Position(line, column) => {
+            let line = line.saturating_sub(1) as u32;
+            let start = Position::new(line, column.saturating_sub(1) as u32);
+            Some(Range::new(start, document.line_range(line).end.max(start)))
+        }
+        ErrorLocation::Rule(word, rule_index) => definition(word)
+            .and_then(|definition| {
+                document
+                    .rules
+                    .iter()
+                    .filter(|rule| rule.line > definition.first_line && rule.line <= definition.last_line)
+                    .nth(*rule_index)
+            })
+            .map(|rule| document.line_range(rule.line)),
+        ErrorLocation::Regex(text) => document.regexes.iter().find(|regex| &regex.text == text).map(|r| r.range),
+        ErrorLocation::ReferenceIn(word, in_word) => definition(in_word).and_then(|definition| {
+            document
+                .named(word, Kind::Reference)
+                .find(|name| (definition.first_line..=definition.last_line).contains(&name.range.start.line))
+                .map(|name| name.range)
+        }),
+        ErrorLocation::Definition(word) => name(word, Kind::Definition),
+        ErrorLocation::Reference(word) => name(word, Kind::Reference),
+        ErrorLocation::Binding(word) => name(word, Kind::Binding),
+        ErrorLocation::Use(word) => name(word, Kind::Use),
+        ErrorLocation::Function(function) => name(function, Kind::Function),
+    };
+    range.unwrap_or_else(|| document.line_range(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::{DiagnosticSeverity, Position};
+
+    use super::diagnostics;
+    use crate::document::Document;
+
+    /// Gives the start of the only diagnostic of a grammar, which has to be an error.
+    fn error_start(grammar: &str) -> Position {
+        let diagnostics = diagnostics(&Document::new(grammar.to_string()));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::ERROR));
+        diagnostics[0].range.start
+    }
+
+    #[test]
+    fn errors_are_shown_where_they_are_about() {
+        assert_eq!(error_start("A:\n  \"a\" -> \"a\"\n  B=B -> B"), Position::new(2, 2));
+        assert_eq!(error_start("A:\n  \"a\" -> \"a\"\n  \"(?P<v>b)\" -> \"b\""), Position::new(2, 7));
+        assert_eq!(error_start("A:\n  \"a\" -> \"a\"\n  \"b\" -> reverse(\"b\")"), Position::new(2, 9));
+        assert_eq!(error_start("A:\n  \"a\" -> \"a\"\n  [\"x*\" A=A \"y\"] -> A"), Position::new(2, 0));
+        assert_eq!(error_start("A:\n  \"a\" -> \"a\"\n  \"b\" ->"), Position::new(2, 0));
+    }
+
+    #[test]
+    fn warnings_are_shown_at_their_words() {
+        let diagnostics = diagnostics(&Document::new("A:\n  \"a\" -> \"a\"\nB:\n  \"b\" -> \"b\"".to_string()));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::WARNING));
+        assert_eq!(diagnostics[0].range.start, Position::new(2, 0));
+    }
+}
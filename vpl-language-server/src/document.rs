@@ -0,0 +1,300 @@
+//! An index of the names in a grammar file and their positions, which is built without parsing the grammar, so it
+//! is also available while the file has errors.
+
+use lsp_types::{Position, Range};
+use regex::Regex;
+
+/// The role of a name in the grammar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// The name of a nonterminal before the `:`.
+    Definition,
+    /// The nonterminal of an identifier `Nonterminal=name` in the source of a rule.
+    Reference,
+    /// The name of an identifier after the `=`, or the name of a capture group.
+    Binding,
+    /// A name in the transform of a rule.
+    Use,
+    /// The name of a function in the transform of a rule.
+    Function,
+}
+
+/// A name in the grammar, with the rule it is part of.
+#[derive(Debug, Clone)]
+pub struct Name {
+    pub text: String,
+    pub kind: Kind,
+    pub range: Range,
+    pub rule: Option<usize>,
+}
+
+/// A rule of a nonterminal, which is a single line.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub line: u32,
+    /// The column of the `->` between the source and the transform.
+    pub arrow: Option<u32>,
+}
+
+/// A nonterminal, with the lines of its definition.
+#[derive(Debug, Clone)]
+pub struct Definition {
+    pub name: String,
+    pub first_line: u32,
+    pub last_line: u32,
+}
+
+/// A regular expression in the source of a rule.
+#[derive(Debug, Clone)]
+pub struct RegexString {
+    pub text: String,
+    pub range: Range,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TokenKind {
+    Word,
+    String,
+    Arrow,
+    Punctuation(char),
+}
+
+/// A token of a line, with its columns in UTF-16 code units as in the positions of the protocol.
+#[derive(Debug, Clone)]
+struct Token<'a> {
+    kind: TokenKind,
+    /// The text of the token, without the quotes of a string.
+    text: &'a str,
+    start: u32,
+    end: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Document {
+    pub text: String,
+    pub names: Vec<Name>,
+    pub rules: Vec<Rule>,
+    pub definitions: Vec<Definition>,
+    pub regexes: Vec<RegexString>,
+}
+
+impl Document {
+    pub fn new(text: String) -> Self {
+        let mut document = Document {
+            text: String::new(),
+            ..Default::default()
+        };
+        for (line_number, line) in text.lines().enumerate() {
+            document.add_line(line_number as u32, line);
+        }
+        document.text = text;
+        document
+    }
+
+    fn add_line(&mut self, line_number: u32, line: &str) {
+        let tokens = tokenize(line);
+        if line.starts_with(char::is_alphanumeric) {
+            if let Some(token) = tokens.first() {
+                self.names.push(Name {
+                    text: token.text.to_string(),
+                    kind: Kind::Definition,
+                    range: range(line_number, token.start, token.end),
+                    rule: None,
+                });
+                self.definitions.push(Definition {
+                    name: token.text.to_string(),
+                    first_line: line_number,
+                    last_line: line_number,
+                });
+            }
+            return;
+        }
+        let Some(definition) = self.definitions.last_mut() else {
+            return;
+        };
+        if tokens.is_empty() {
+            return;
+        }
+        definition.last_line = line_number;
+        let rule = Some(self.rules.len());
+        let arrow = tokens.iter().position(|token| token.kind == TokenKind::Arrow);
+        let (source, transform) = tokens.split_at(arrow.unwrap_or(tokens.len()));
+        self.rules.push(Rule {
+            line: line_number,
+            arrow: arrow.map(|index| tokens[index].start),
+        });
+        let name = |token: &Token, kind| Name {
+            text: token.text.to_string(),
+            kind,
+            range: range(line_number, token.start, token.end),
+            rule,
+        };
+        let mut index = 0;
+        while index < source.len() {
+            match &source[index..] {
+                [nonterminal, equals, binding, ..]
+                    if nonterminal.kind == TokenKind::Word
+                        && equals.kind == TokenKind::Punctuation('=')
+                        && binding.kind == TokenKind::Word =>
+                {
+                    self.names.push(name(nonterminal, Kind::Reference));
+                    self.names.push(name(binding, Kind::Binding));
+                    index += 3;
+                    continue;
+                }
+                [string, ..] if string.kind == TokenKind::String => {
+                    for (capture, start) in capture_names(string.text) {
+                        let start = string.start + 1 + utf16_len(&string.text[..start]);
+                        let end = start + utf16_len(&capture);
+                        self.names.push(Name {
+                            text: capture,
+                            kind: Kind::Binding,
+                            range: range(line_number, start, end),
+                            rule,
+                        });
+                    }
+                    self.regexes.push(RegexString {
+                        text: string.text.to_string(),
+                        range: range(line_number, string.start, string.end),
+                    });
+                }
+                _ => {}
+            }
+            index += 1;
+        }
+        for (index, token) in transform.iter().enumerate() {
+            if token.kind != TokenKind::Word {
+                continue;
+            }
+            let is_call = transform
+                .get(index + 1)
+                .is_some_and(|next| next.kind == TokenKind::Punctuation('('));
+            self.names.push(name(token, if is_call { Kind::Function } else { Kind::Use }));
+        }
+    }
+
+    /// Gives the name at a position, where a position directly after a name is also part of it.
+    pub fn name_at(&self, position: Position) -> Option<&Name> {
+        self.names.iter().find(|name| {
+            name.range.start.line == position.line
+                && name.range.start.character <= position.character
+                && position.character <= name.range.end.character
+        })
+    }
+
+    /// Gives the name of the nonterminal at a position, either at its definition or at a reference.
+    pub fn nonterminal_at(&self, position: Position) -> Option<&str> {
+        self.name_at(position)
+            .filter(|name| matches!(name.kind, Kind::Definition | Kind::Reference))
+            .map(|name| name.text.as_str())
+    }
+
+    /// Gives the definition of a nonterminal.
+    pub fn definition(&self, nonterminal: &str) -> Option<&Name> {
+        self.names
+            .iter()
+            .find(|name| name.kind == Kind::Definition && name.text == nonterminal)
+    }
+
+    /// Gives the names of a kind with the given text.
+    pub fn named<'a>(&'a self, text: &'a str, kind: Kind) -> impl Iterator<Item = &'a Name> {
+        self.names
+            .iter()
+            .filter(move |name| name.kind == kind && name.text == text)
+    }
+
+    /// Gives the bindings of a rule, which are the names that can be used in its transform.
+    pub fn bindings(&self, rule: usize) -> impl Iterator<Item = &Name> {
+        self.names
+            .iter()
+            .filter(move |name| name.kind == Kind::Binding && name.rule == Some(rule))
+    }
+
+    /// Gives the index of the rule on a line.
+    pub fn rule_at(&self, line: u32) -> Option<usize> {
+        self.rules.iter().position(|rule| rule.line == line)
+    }
+
+    /// Gives the lines of the definition of a nonterminal, with its rules.
+    pub fn definition_text(&self, nonterminal: &str) -> Option<String> {
+        let definition = self.definitions.iter().find(|d| d.name == nonterminal)?;
+        let lines: Vec<&str> = self
+            .text
+            .lines()
+            .skip(definition.first_line as usize)
+            .take((definition.last_line - definition.first_line + 1) as usize)
+            .collect();
+        Some(lines.join("\n"))
+    }
+
+    /// Gives the range of a whole line.
+    pub fn line_range(&self, line: u32) -> Range {
+        let length = self.text.lines().nth(line as usize).map_or(0, utf16_len);
+        range(line, 0, length)
+    }
+}
+
+fn range(line: u32, start: u32, end: u32) -> Range {
+    Range::new(Position::new(line, start), Position::new(line, end))
+}
+
+fn utf16_len(text: &str) -> u32 {
+    text.encode_utf16().count() as u32
+}
+
+/// Splits a line in words, strings, arrows and other punctuation.
+fn tokenize(line: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut chars = line.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let mut end = start + c.len_utf8();
+        let (kind, text) = if c.is_alphanumeric() {
+            while let Some((_, c)) = chars.next_if(|(_, c)| c.is_alphanumeric()) {
+                end += c.len_utf8();
+            }
+            (TokenKind::Word, &line[start..end])
+        } else if c == '"' {
+            let mut escaped = false;
+            for (_, c) in chars.by_ref() {
+                end += c.len_utf8();
+                if c == '"' && !escaped {
+                    break;
+                }
+                escaped = c == '\\' && !escaped;
+            }
+            let text = line[start + 1..end].strip_suffix('"').unwrap_or(&line[start + 1..end]);
+            (TokenKind::String, text)
+        } else if c == '-' && chars.next_if(|(_, c)| *c == '>').is_some() {
+            end += 1;
+            (TokenKind::Arrow, &line[start..end])
+        } else if c.is_whitespace() {
+            continue;
+        } else {
+            (TokenKind::Punctuation(c), &line[start..end])
+        };
+        tokens.push(Token {
+            kind,
+            text,
+            start: utf16_len(&line[..start]),
+            end: utf16_len(&line[..end]),
+        });
+    }
+    tokens
+}
+
+/// Gives the names of the capture groups of a regular expression, with their byte offsets in it.
+fn capture_names(regex: &str) -> Vec<(String, usize)> {
+    let Ok(regex) = Regex::new(regex) else {
+        return Vec::new();
+    };
+    let mut names = Vec::new();
+    let mut from = 0;
+    for name in regex.capture_names().flatten() {
+        let pattern = format!("<{name}>");
+        if let Some(offset) = regex.as_str()[from..].find(&pattern) {
+            names.push((name.to_string(), from + offset + 1));
+            from += offset + pattern.len();
+        }
+    }
+    names
+}
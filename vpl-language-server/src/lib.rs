@@ -0,0 +1,308 @@
+//! A language server for `.vpa` grammar files of [`vpl_parser_generator`].
+//!
+//! The server reports the errors and warnings of a grammar while it is edited, and knows the nonterminals
+//! of the grammar: it goes to the definition of a nonterminal and finds its references, shows its rules on hover,
+//! completes nonterminal names in sources and bound names in transforms, and renames a nonterminal in the whole
+//! file. The `vpl-language-server` binary runs it over standard input and output; [`run`] runs it on any
+//! [`Connection`], such as an in-process one for tests.
+
+use std::{collections::HashMap, error::Error};
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+        Notification as _, PublishDiagnostics,
+    },
+    request::{Completion, GotoDefinition, HoverRequest, References, Rename, Request as _},
+    CompletionItem, CompletionItemKind, CompletionParams, CompletionResponse,
+    GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents, HoverParams,
+    HoverProviderCapability, Location, MarkupContent, MarkupKind, OneOf, PublishDiagnosticsParams,
+    ReferenceParams, RenameParams, ServerCapabilities, TextDocumentSyncCapability,
+    TextDocumentSyncKind, TextEdit, Url, WorkspaceEdit,
+};
+use vpl_parser_generator::FunctionRegistry;
+
+use crate::document::{Document, Kind};
+
+mod diagnostics;
+mod document;
+
+/// The functions that are known to every translator, besides the registered ones.
+const BUILT_IN_FUNCTIONS: &[&str] = &["join"];
+
+/// Runs the language server on a connection until the client shuts it down.
+///
+/// # Examples
+///
+/// The server can be tested with an in-process client:
+///
+/// ```
+/// use lsp_server::{Connection, Message, Notification, Request};
+/// use serde_json::json;
+///
+/// let (server, client) = Connection::memory();
+/// let server = std::thread::spawn(move || vpl_language_server::run(server).unwrap());
+/// let request = |id: i32, method: &str, params| {
+///     client.sender.send(Request::new(id.into(), method.into(), params).into()).unwrap();
+///     match client.receiver.recv().unwrap() {
+///         Message::Response(response) => response.result.unwrap(),
+///         message => panic!("Unexpected {message:?}"),
+///     }
+/// };
+/// let notify = |method: &str, params| client.sender.send(Notification::new(method.into(), params).into()).unwrap();
+///
+/// request(1, "initialize", json!({ "capabilities": {} }));
+/// notify("initialized", json!({}));
+/// let uri = "file:///grammar.vpa";
+/// let text = "A:\n  B=b C=c -> b c\nB:\n  \"(?P<x>b)\" -> x\n";
+/// notify("textDocument/didOpen", json!({
+///     "textDocument": { "uri": uri, "languageId": "vpa", "version": 1, "text": text }
+/// }));
+/// let Message::Notification(diagnostics) = client.receiver.recv().unwrap() else { panic!() };
+/// assert_eq!(diagnostics.params["diagnostics"][0]["message"], "Word C is not defined");
+/// assert_eq!(diagnostics.params["diagnostics"][0]["range"]["start"], json!({ "line": 1, "character": 6 }));
+///
+/// let position = json!({ "textDocument": { "uri": uri }, "position": { "line": 1, "character": 2 } });
+/// let definition = request(2, "textDocument/definition", position.clone());
+/// assert_eq!(definition["range"]["start"], json!({ "line": 2, "character": 0 }));
+/// let hover = request(3, "textDocument/hover", position.clone());
+/// assert_eq!(hover["contents"]["value"], "```vpa\nB:\n  \"(?P<x>b)\" -> x\n```");
+/// let mut rename = position.clone();
+/// rename["newName"] = json!("Bee");
+/// let edit = request(4, "textDocument/rename", rename);
+/// assert_eq!(edit["changes"][uri].as_array().unwrap().len(), 2);
+///
+/// let transform = json!({ "textDocument": { "uri": uri }, "position": { "line": 3, "character": 17 } });
+/// let completion = request(5, "textDocument/completion", transform);
+/// assert!(completion.as_array().unwrap().iter().any(|item| item["label"] == "x"));
+///
+/// request(6, "shutdown", json!(null));
+/// notify("exit", json!(null));
+/// server.join().unwrap();
+/// ```
+pub fn run(connection: Connection) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        definition_provider: Some(OneOf::Left(true)),
+        references_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(Default::default()),
+        rename_provider: Some(OneOf::Left(true)),
+        ..Default::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+    let mut server = Server {
+        documents: HashMap::new(),
+        functions: FunctionRegistry::default(),
+    };
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    return Ok(());
+                }
+                connection.sender.send(server.request(request).into())?;
+            }
+            Message::Notification(notification) => {
+                if let Some(diagnostics) = server.notification(notification) {
+                    connection.sender.send(diagnostics.into())?;
+                }
+            }
+            Message::Response(_) => {}
+        }
+    }
+    Ok(())
+}
+
+struct Server {
+    documents: HashMap<Url, Document>,
+    functions: FunctionRegistry,
+}
+
+impl Server {
+    /// Handles a notification about a document, and gives the diagnostics to publish for it.
+    fn notification(&mut self, notification: Notification) -> Option<Notification> {
+        let (uri, text) = match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params = extract::<DidOpenTextDocument>(notification)?;
+                (params.text_document.uri, Some(params.text_document.text))
+            }
+            DidChangeTextDocument::METHOD => {
+                let params = extract::<DidChangeTextDocument>(notification)?;
+                let text = params.content_changes.into_iter().last()?.text;
+                (params.text_document.uri, Some(text))
+            }
+            DidCloseTextDocument::METHOD => {
+                let params = extract::<DidCloseTextDocument>(notification)?;
+                (params.text_document.uri, None)
+            }
+            _ => return None,
+        };
+        let diagnostics = match text {
+            Some(text) => {
+                let document = Document::new(text);
+                let diagnostics = diagnostics::diagnostics(&document);
+                self.documents.insert(uri.clone(), document);
+                diagnostics
+            }
+            None => {
+                self.documents.remove(&uri);
+                Vec::new()
+            }
+        };
+        let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+        Some(Notification::new(PublishDiagnostics::METHOD.to_string(), params))
+    }
+
+    fn request(&mut self, request: Request) -> Response {
+        match request.method.as_str() {
+            GotoDefinition::METHOD => respond::<GotoDefinition>(request, |p| self.definition(p)),
+            References::METHOD => respond::<References>(request, |p| self.references(p)),
+            HoverRequest::METHOD => respond::<HoverRequest>(request, |p| self.hover(p)),
+            Completion::METHOD => respond::<Completion>(request, |p| self.completion(p)),
+            Rename::METHOD => respond::<Rename>(request, |p| self.rename(p)),
+            method => Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("Method {method} is not supported"),
+            ),
+        }
+    }
+
+    fn document(&self, uri: &Url) -> Result<&Document, String> {
+        self.documents
+            .get(uri)
+            .ok_or_else(|| format!("Document {uri} is not open"))
+    }
+
+    /// Goes from a nonterminal to its definition, or from a name in a transform to where it is bound.
+    fn definition(&self, params: GotoDefinitionParams) -> Result<Option<GotoDefinitionResponse>, String> {
+        let position = params.text_document_position_params;
+        let uri = position.text_document.uri;
+        let document = self.document(&uri)?;
+        let Some(name) = document.name_at(position.position) else {
+            return Ok(None);
+        };
+        let target = match name.kind {
+            Kind::Definition | Kind::Reference => document.definition(&name.text),
+            Kind::Use => name.rule.and_then(|rule| {
+                document
+                    .bindings(rule)
+                    .find(|binding| binding.text == name.text)
+            }),
+            Kind::Binding | Kind::Function => None,
+        };
+        Ok(target.map(|target| GotoDefinitionResponse::Scalar(Location::new(uri, target.range))))
+    }
+
+    fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>, String> {
+        let position = params.text_document_position;
+        let uri = position.text_document.uri;
+        let document = self.document(&uri)?;
+        let Some(nonterminal) = document.nonterminal_at(position.position) else {
+            return Ok(None);
+        };
+        let mut locations = Vec::new();
+        if params.context.include_declaration {
+            locations.extend(document.named(nonterminal, Kind::Definition));
+        }
+        locations.extend(document.named(nonterminal, Kind::Reference));
+        let locations = locations
+            .into_iter()
+            .map(|name| Location::new(uri.clone(), name.range))
+            .collect();
+        Ok(Some(locations))
+    }
+
+    /// Shows the rules of a nonterminal.
+    fn hover(&self, params: HoverParams) -> Result<Option<Hover>, String> {
+        let position = params.text_document_position_params;
+        let document = self.document(&position.text_document.uri)?;
+        let Some(nonterminal) = document.nonterminal_at(position.position) else {
+            return Ok(None);
+        };
+        Ok(document.definition_text(nonterminal).map(|text| Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!("```vpa\n{text}\n```"),
+            }),
+            range: None,
+        }))
+    }
+
+    /// Completes nonterminals in the source of a rule, and bound names and functions in its transform.
+    fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>, String> {
+        let position = params.text_document_position.position;
+        let document = self.document(&params.text_document_position.text_document.uri)?;
+        let Some(rule_index) = document.rule_at(position.line) else {
+            return Ok(None);
+        };
+        let item = |label: &str, kind| CompletionItem {
+            label: label.to_string(),
+            kind: Some(kind),
+            ..Default::default()
+        };
+        let in_transform = document.rules[rule_index]
+            .arrow
+            .is_some_and(|arrow| arrow < position.character);
+        let items = if in_transform {
+            let mut items: Vec<CompletionItem> = document
+                .bindings(rule_index)
+                .map(|binding| item(&binding.text, CompletionItemKind::VARIABLE))
+                .collect();
+            let functions = self.functions.names().into_iter().chain(BUILT_IN_FUNCTIONS.iter().copied());
+            items.extend(functions.map(|function| item(function, CompletionItemKind::FUNCTION)));
+            items
+        } else {
+            document
+                .definitions
+                .iter()
+                .map(|definition| item(&definition.name, CompletionItemKind::CLASS))
+                .collect()
+        };
+        Ok(Some(CompletionResponse::Array(items)))
+    }
+
+    /// Renames a nonterminal at its definition and all its references.
+    fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>, String> {
+        let position = params.text_document_position;
+        let uri = position.text_document.uri;
+        let document = self.document(&uri)?;
+        let Some(nonterminal) = document.nonterminal_at(position.position) else {
+            return Ok(None);
+        };
+        let new_name = params.new_name;
+        if new_name.is_empty() || !new_name.chars().all(char::is_alphanumeric) {
+            return Err(format!("{new_name} is not a valid name of a nonterminal"));
+        }
+        if document.definition(&new_name).is_some() {
+            return Err(format!("Word {new_name} is already defined"));
+        }
+        let edits = document
+            .named(nonterminal, Kind::Definition)
+            .chain(document.named(nonterminal, Kind::Reference))
+            .map(|name| TextEdit::new(name.range, new_name.clone()))
+            .collect();
+        Ok(Some(WorkspaceEdit::new(HashMap::from([(uri, edits)]))))
+    }
+}
+
+fn extract<N: lsp_types::notification::Notification>(notification: Notification) -> Option<N::Params> {
+    serde_json::from_value(notification.params).ok()
+}
+
+/// Answers a request with the result of a handler, or with an error if its parameters or the handler fail.
+fn respond<R: lsp_types::request::Request>(
+    request: Request,
+    handler: impl FnOnce(R::Params) -> Result<R::Result, String>,
+) -> Response {
+    let params = match serde_json::from_value(request.params) {
+        Ok(params) => params,
+        Err(e) => return Response::new_err(request.id, ErrorCode::InvalidParams as i32, e.to_string()),
+    };
+    match handler(params) {
+        Ok(result) => Response::new_ok(request.id, result),
+        Err(message) => Response::new_err(request.id, ErrorCode::RequestFailed as i32, message),
+    }
+}
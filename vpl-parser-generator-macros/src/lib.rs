@@ -0,0 +1,190 @@
+//! Procedural macros that build the recognizer and translator of a grammar at compile time.
+//!
+//! The grammar is parsed and checked while compiling, so an invalid grammar is a compiler error at the string of
+//! the grammar, and only the regular expressions are compiled when the program runs.
+
+use std::{env, fs, path::Path};
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, DeriveInput, Expr, Lit, LitStr, Meta};
+use vpl_parser_generator::codegen;
+
+/// Gives the translator of a grammar, with the built-in functions.
+///
+/// # Examples
+///
+/// ```
+/// use vpl_parser_generator_macros::vpl;
+///
+/// let translator = vpl!("A:\n  \"(?P<v>[a-z]+)\" -> upper(v)");
+/// assert_eq!(translator.translate("abc"), Some("ABC".to_string()));
+/// ```
+///
+/// An invalid grammar does not compile:
+///
+/// ```compile_fail
+/// use vpl_parser_generator_macros::vpl;
+///
+/// let translator = vpl!("A:\n  A=A \"a\" -> A");
+/// ```
+#[proc_macro]
+pub fn vpl(input: TokenStream) -> TokenStream {
+    let grammar = parse_macro_input!(input as LitStr);
+    let expanded = match tables(&grammar.value(), &grammar) {
+        Ok((tables, true)) => quote! {{
+            use ::vpl_parser_generator::codegen::tables::{Action, Tables, Transform};
+            static TABLES: Tables = #tables;
+            TABLES
+                .translator(::std::default::Default::default())
+                .expect("The built-in functions are checked when the grammar is compiled")
+        }},
+        Ok((_, false)) => syn::Error::new(
+            grammar.span(),
+            "The grammar uses functions that are not built in, use `#[derive(Grammar)]` to give the functions",
+        )
+        .to_compile_error(),
+        Err(error) => error.to_compile_error(),
+    };
+    expanded.into()
+}
+
+/// Gives the types of the nonterminals of a grammar, like [`codegen::generate_types`], as items of the module it is
+/// used in.
+///
+/// # Examples
+///
+/// ```
+/// mod pair {
+///     vpl_parser_generator_macros::vpl_types!("Pair:\n  \"(?P<key>[a-z]+)=\" Value=V -> key V\nValue:\n  \"[0-9]+\" -> \"n\"");
+/// }
+///
+/// let recognizer = vpl_parser_generator::Recognizer::new("Pair:\n  \"(?P<key>[a-z]+)=\" Value=V -> key V\nValue:\n  \"[0-9]+\" -> \"n\"").unwrap();
+/// let pair::Pair::Rule1 { key, V } = pair::parse(&recognizer, "a=1").unwrap();
+/// assert_eq!(key, "a");
+/// assert_eq!(*V, pair::Value::Rule1);
+/// ```
+#[proc_macro]
+pub fn vpl_types(input: TokenStream) -> TokenStream {
+    let grammar = parse_macro_input!(input as LitStr);
+    let expanded = codegen::generate_types(&grammar.value())
+        .map_err(|error| syn::Error::new(grammar.span(), format!("Invalid grammar: {error}")))
+        .and_then(|code| {
+            code.parse::<TokenStream2>()
+                .map_err(|error| syn::Error::new(grammar.span(), format!("Invalid generated code: {error}")))
+        });
+    expanded.unwrap_or_else(|error| error.to_compile_error()).into()
+}
+
+/// Adds functions that give the recognizer and translator of the grammar in the file of the `grammar` attribute.
+///
+/// The path of the file is relative to the directory of the manifest of the crate. The type gets the functions:
+/// * `recognizer()` gives the recognizer of the grammar.
+/// * `translator()` gives the translator of the grammar with the built-in functions, if it only uses those.
+/// * `translator_with_functions(functions)` gives the translator with the given functions, or an error if a
+///   function of a transform is not registered.
+///
+/// # Examples
+///
+/// ```
+/// use vpl_parser_generator_macros::Grammar;
+///
+/// #[derive(Grammar)]
+/// #[grammar = "../examples/a-lang.vpa"]
+/// struct ALang;
+///
+/// assert!(ALang::recognizer().recognize("aaa").is_some());
+/// assert_eq!(ALang::translator().translate("aaa"), Some("b".to_string()));
+/// ```
+#[proc_macro_derive(Grammar, attributes(grammar))]
+pub fn derive_grammar(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    derive(&input)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+fn derive(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let path = grammar_attribute(input)?;
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+    let file = Path::new(&manifest_dir).join(path.value());
+    let grammar = fs::read_to_string(&file).map_err(|error| {
+        syn::Error::new(
+            path.span(),
+            format!("Grammar file {} can not be read: {error}", file.display()),
+        )
+    })?;
+    let (tables, built_in) = tables(&grammar, &path)?;
+    let file = file.to_string_lossy();
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let translator = built_in.then(|| {
+        quote! {
+            /// Gives the translator of the grammar, with the built-in functions.
+            pub fn translator() -> ::vpl_parser_generator::Translator {
+                Self::translator_with_functions(::std::default::Default::default())
+                    .expect("The built-in functions are checked when the grammar is compiled")
+            }
+        }
+    });
+    Ok(quote! {
+        impl #impl_generics #name #type_generics #where_clause {
+            /// Gives the recognizer of the grammar.
+            pub fn recognizer() -> ::vpl_parser_generator::Recognizer {
+                Self::grammar_tables().recognizer()
+            }
+
+            #translator
+
+            /// Gives the translator of the grammar, which uses the given functions in its transforms.
+            pub fn translator_with_functions(
+                functions: ::vpl_parser_generator::FunctionRegistry,
+            ) -> ::std::result::Result<::vpl_parser_generator::Translator, ::std::string::String> {
+                Self::grammar_tables().translator(functions)
+            }
+
+            fn grammar_tables() -> &'static ::vpl_parser_generator::codegen::tables::Tables {
+                // Compiles the type again when the grammar file changes.
+                const _: &str = include_str!(#file);
+                use ::vpl_parser_generator::codegen::tables::{Action, Tables, Transform};
+                static TABLES: Tables = #tables;
+                &TABLES
+            }
+        }
+    })
+}
+
+/// Gives the path of `#[grammar = "..."]`.
+fn grammar_attribute(input: &DeriveInput) -> syn::Result<LitStr> {
+    for attribute in &input.attrs {
+        if !attribute.path().is_ident("grammar") {
+            continue;
+        }
+        if let Meta::NameValue(name_value) = &attribute.meta {
+            if let Expr::Lit(expr) = &name_value.value {
+                if let Lit::Str(path) = &expr.lit {
+                    return Ok(path.clone());
+                }
+            }
+        }
+        return Err(syn::Error::new_spanned(
+            attribute,
+            "Expected the path of a grammar file, like #[grammar = \"grammar.vpa\"]",
+        ));
+    }
+    Err(syn::Error::new_spanned(
+        &input.ident,
+        "Expected the path of a grammar file, like #[grammar = \"grammar.vpa\"]",
+    ))
+}
+
+/// Checks the grammar and gives the tables of its recognizer, with errors at the span of the grammar string.
+fn tables(grammar: &str, span: &LitStr) -> syn::Result<(TokenStream2, bool)> {
+    let (tables, built_in) = codegen::generate_tables(grammar)
+        .map_err(|error| syn::Error::new(span.span(), format!("Invalid grammar: {error}")))?;
+    let tables = tables
+        .parse()
+        .map_err(|error| syn::Error::new(span.span(), format!("Invalid generated code: {error}")))?;
+    Ok((tables, built_in))
+}
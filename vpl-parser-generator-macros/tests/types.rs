@@ -0,0 +1,23 @@
+//! Compiles the types of grammars whose words are named like Rust keywords and types.
+
+use vpl_parser_generator::Recognizer;
+
+const GRAMMAR: &str = "type:\n  \"(?P<Self>[a-z]+)=\" Self=String -> Self String\nSelf:\n  \"(?P<fn>[0-9]+)\" -> fn\n  [\"\\(\" Option=1 \"\\)\"] -> 1\nOption:\n  nodes=Box? \";\" -> Box\nnodes:\n  \"!\" -> \"!\"\n1st:\n  \"x\" -> \"x\"";
+
+mod types {
+    vpl_parser_generator_macros::vpl_types!("type:\n  \"(?P<Self>[a-z]+)=\" Self=String -> Self String\nSelf:\n  \"(?P<fn>[0-9]+)\" -> fn\n  [\"\\(\" Option=1 \"\\)\"] -> 1\nOption:\n  nodes=Box? \";\" -> Box\nnodes:\n  \"!\" -> \"!\"\n1st:\n  \"x\" -> \"x\"");
+}
+
+#[test]
+fn words_named_like_rust_keywords_and_types() {
+    let recognizer = Recognizer::new(GRAMMAR).unwrap();
+    let parsed = types::parse(&recognizer, "key=(!;)").unwrap();
+    let types::r#type::Rule1 { Self_, String } = parsed;
+    assert_eq!(Self_, "key");
+    let types::Self_::Rule2 { _1 } = *String else {
+        panic!("Expected the second rule of Self");
+    };
+    let types::Option::Rule1 { Box } = *_1;
+    assert_eq!(Box.map(|nodes| *nodes), Some(types::nodes::Rule1));
+    let _: types::_1st = types::_1st::Rule1;
+}